@@ -5,44 +5,190 @@ pub use self::additional::{
     AdditionalRef,
 };
 use ink_lang as ink;
+use ink_env::AccountId;
+use coinday::{Award, Coinday, CoindayLedger};
+
+/// Lets `coinday::Distribution` drive this stream through a contract reference.
+impl CoindayLedger for AdditionalRef {
+    fn coinday_of(&self, user: AccountId) -> Coinday {
+        self.get_coinday_info(user)
+    }
+
+    fn award_at(&self, index: u32) -> Option<Award> {
+        self.try_get_award(index)
+    }
+
+    fn awards_len(&self) -> u32 {
+        self.awards_length()
+    }
+
+    fn stream_coinday(&self) -> (u128, u128) {
+        self.total_coinday()
+    }
+
+    fn stream_reward(&self) -> u128 {
+        self.total_reward()
+    }
+
+    fn reward_balance(&self, user: AccountId) -> u128 {
+        self.reward_of(user)
+    }
+
+    fn set_coinday(&mut self, user: AccountId, info: Coinday) {
+        assert!(self.update_coindays(user, info.amount, info.timestamp, info.last_index).is_ok());
+    }
+
+    fn remove_coinday(&mut self, user: AccountId) {
+        assert!(self.remove_coindays(user).is_ok());
+    }
+
+    fn set_stream_coinday(&mut self, new_value: (u128, u128)) {
+        assert!(self.update_total_coinday(new_value).is_ok());
+    }
+
+    fn set_stream_reward(&mut self, new_value: u128) {
+        assert!(self.update_total_reward(new_value).is_ok());
+    }
+
+    fn set_reward_balance(&mut self, user: AccountId, value: u128) {
+        assert!(self.update_rewards(user, value).is_ok());
+    }
+
+    fn push_award(&mut self, award: Award) {
+        assert!(self.update_awards(award.amount, award.total_coinday, award.timestamp).is_ok());
+    }
+}
 
 #[ink::contract]
 mod additional {
-    // #[cfg(not(feature = "ink-as-dependency"))]
-    use ink_storage::collections::HashMap as StorageHashMap;
-    // #[cfg(not(feature = "ink-as-dependency"))]
-    use ink_prelude::vec::Vec;
-    use ink_storage::traits::{SpreadLayout, PackedLayout};
+    pub use coinday::{Coinday, Award, Checkpoint, Scope, Vesting, VestingConfig};
+    use coinday::Ledger;
+    use elc::ELCRef;
+    use ink_storage::lazy::Lazy;
+    use ink_env::call::FromAccountId;
 
     /// The error types
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         OnlyOwnerAccess,
+        OnlyWriterAccess,
+        PeriodCompacted,
+        InvalidVestingConfig,
+        NothingToClaim,
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
 
-    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Coinday {
-        // coinday of user
-        pub amount: u128,
-        // last time for update coinday
-        pub timestamp: u128,
-        // awards' index: record the index of award already got.
-        pub last_index: u32,
+    /// Event emitted when the owner grants or revokes a write scope.
+    #[ink(event)]
+    pub struct WriterUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        scope: Scope,
+        allowed: bool,
     }
 
-    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Award {
-        // current elc increase amount
-        pub amount: u128,
-        // total coinday at current timestamp
-        pub total_coinday: u128,
-        // current timestamp
-        pub timestamp: u128,
+    #[ink(event)]
+    pub struct TotalRewardUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        value: u128,
+    }
+
+    #[ink(event)]
+    pub struct RewardUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+        value: u128,
+    }
+
+    #[ink(event)]
+    pub struct TotalCoindayUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        total_coinday: u128,
+        timestamp: u128,
+    }
+
+    #[ink(event)]
+    pub struct CoindayUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        timestamp: u128,
+        last_index: u32,
+    }
+
+    #[ink(event)]
+    pub struct CoindayRemoved {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AwardsCompacted {
+        #[ink(topic)]
+        writer: AccountId,
+        folded: u32,
+        checkpoint_index: u32,
+    }
+
+    #[ink(event)]
+    pub struct AwardPublished {
+        #[ink(topic)]
+        writer: AccountId,
+        index: u32,
+        amount: u128,
+        total_coinday: u128,
+        timestamp: u128,
+    }
+
+    #[ink(event)]
+    pub struct VestingConfigUpdated {
+        enabled: bool,
+        cliff: u128,
+        duration: u128,
+        penalty: u128,
+    }
+
+    /// Event emitted when claimed ELC of a user is locked into vesting.
+    #[ink(event)]
+    pub struct Vested {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        from_penalty_pool: u128,
+    }
+
+    #[ink(event)]
+    pub struct VestingWithdrawn {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct VestingExited {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        penalty: u128,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
     }
 
     /// Defines the storage of your contract.
@@ -50,16 +196,12 @@ mod additional {
     /// to add new static storage fields to your contract.
     #[ink(storage)]
     pub struct Additional {
-        /// Total reward.
-        total_reward: u128,
-        /// Mapping from owner to reward owned.
-        rewards: StorageHashMap<AccountId, u128>,
-        /// Total coinday (total_coinday, last_update_time)
-        total_coinday: (u128, u128),
-        /// Mapping from owner to a tuple (coinday, last_update_time)
-        coindays: StorageHashMap<AccountId, Coinday>,
-        /// award info of each elc-increase period
-        awards: Vec<Award>,
+        /// rewards, coindays and award info of each elc-increase period
+        ledger: Ledger,
+        /// elc token contract, vested ELC is held by this contract.
+        elc_contract: Lazy<ELCRef>,
+        /// ELC of exit penalties held by this contract, used to cover later vested rewards.
+        penalty_pool: u128,
         /// The contract owner
         owner: AccountId,
     }
@@ -67,82 +209,110 @@ mod additional {
     impl Additional {
         /// Constructor that initializes the `bool` value to the given `init_value`.
         #[ink(constructor)]
-        pub fn new() -> Self {
+        pub fn new(elc_token: AccountId) -> Self {
             let now_time = Self::env().block_timestamp().into();
-            let awards: Vec<Award> = Vec::new();
             let owner: AccountId = Self::env().caller();
-            let coinday_info = Coinday {
-                amount: 0, 
-                timestamp: now_time, 
-                last_index: 0 
-            };
-            let mut coindays = StorageHashMap::new();
-            coindays.insert(owner, coinday_info);
+            let elc_contract: ELCRef = FromAccountId::from_account_id(elc_token);
             Self {
-                total_reward: 0,
-                rewards: StorageHashMap::new(),
-                total_coinday: (0, now_time),
-                coindays,
-                awards,
+                ledger: Ledger::new(now_time),
+                elc_contract: Lazy::new(elc_contract),
+                penalty_pool: 0,
                 owner,
             }
         }
 
         #[ink(message)]
         pub fn total_reward(&self) -> u128 {
-            self.total_reward
+            self.ledger.total_reward()
         }
 
         #[ink(message)]
         pub fn reward_of(&self, user: AccountId) -> u128 {
-            self.rewards.get(&user).copied().unwrap_or(0)
+            self.ledger.reward_of(&user)
         }
 
         #[ink(message)]
         pub fn total_coinday(&self) -> (u128, u128) {
-            self.total_coinday
+            self.ledger.total_coinday()
         }
 
+        /// Award of period `index`, panics if the period is not published or already folded.
         #[ink(message)]
         pub fn get_award(&self, index: u32) -> Award {
-            self.awards[index as usize].clone()
+            self.ledger.get_award(index).expect("Award period not found")
+        }
+
+        /// `None` if the period is not published or already folded.
+        #[ink(message)]
+        pub fn try_get_award(&self, index: u32) -> Option<Award> {
+            self.ledger.get_award(index)
         }
 
         #[ink(message)]
         pub fn awards_length(&self) -> u32 {
-            self.awards.len() as u32
+            self.ledger.awards_length()
+        }
+
+        /// Fully settled periods folded so far, awards are only available from its index.
+        #[ink(message)]
+        pub fn checkpoint(&self) -> Checkpoint {
+            self.ledger.checkpoint()
         }
 
         #[ink(message)]
         pub fn get_coinday_info(&self, user: AccountId) -> Coinday {
             let now_time = self.env().block_timestamp().into();
-            let coinday_info = Coinday {
-                amount: 0, 
-                timestamp: now_time, 
-                last_index: 0 
-            };
-            let v = self.coindays.get(&user).unwrap_or(&coinday_info);
-            (*v).clone()
+            self.ledger.get_coinday_info(&user, now_time)
         }
 
+        /// Whether `writer` is allowed to write in `scope`, the owner is allowed in all scopes.
         #[ink(message)]
-        pub fn update_total_reward(&mut self, new_value: u128) -> Result<()> {
+        pub fn is_writer(&self, writer: AccountId, scope: Scope) -> bool {
+            writer == self.owner || self.ledger.has_scope(&writer, scope)
+        }
+
+        /// Allow `writer`(e.g. RELP or Stable contract) to write in `scope`.
+        #[ink(message)]
+        pub fn grant_writer(&mut self, writer: AccountId, scope: Scope) -> Result<()> {
             self.only_owner()?;
-            self.total_reward = new_value;
+            self.ledger.set_scope(writer, scope, true);
+            self.env().emit_event(WriterUpdated { writer, scope, allowed: true });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_writer(&mut self, writer: AccountId, scope: Scope) -> Result<()> {
+            self.only_owner()?;
+            self.ledger.set_scope(writer, scope, false);
+            self.env().emit_event(WriterUpdated { writer, scope, allowed: false });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn update_total_reward(&mut self, new_value: u128) -> Result<()> {
+            let writer = self.only_writer(Scope::AwardPublisher)?;
+            self.ledger.set_total_reward(new_value);
+            self.env().emit_event(TotalRewardUpdated { writer, value: new_value });
             Ok(())
         } 
 
         #[ink(message)]
         pub fn update_rewards(&mut self, user: AccountId, value: u128) -> Result<()> {
-            self.only_owner()?;
-            self.rewards.insert(user, value);
+            let writer = self.only_writer(Scope::RewardCrediter)?;
+            self.ledger.set_reward(user, value);
+            self.env().emit_event(RewardUpdated { writer, user, value });
             Ok(())
         } 
 
         #[ink(message)]
         pub fn update_total_coinday(&mut self, new_value: (u128, u128)) -> Result<()> {
-            self.only_owner()?;
-            self.total_coinday = new_value;
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            self.ledger.set_total_coinday(new_value);
+            self.env().emit_event(TotalCoindayUpdated {
+                writer,
+                total_coinday: new_value.0,
+                timestamp: new_value.1,
+            });
             Ok(())
         } 
 
@@ -154,13 +324,32 @@ mod additional {
             timestamp: u128,
             index: u32
         ) -> Result<()> {
-            self.only_owner()?;
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            if index < self.ledger.checkpoint().index {
+                return Err(Error::PeriodCompacted)
+            }
             let info = Coinday {
                 amount: coinday,
                 timestamp,
                 last_index: index
             };
-            self.coindays.insert(user, info);
+            self.ledger.set_coinday(user, info);
+            self.env().emit_event(CoindayUpdated {
+                writer,
+                user,
+                amount: coinday,
+                timestamp,
+                last_index: index,
+            });
+            Ok(())
+        }
+
+        /// Remove the coinday record of `user` once it holds nothing.
+        #[ink(message)]
+        pub fn remove_coindays(&mut self, user: AccountId) -> Result<()> {
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            self.ledger.remove_coinday(&user);
+            self.env().emit_event(CoindayRemoved { writer, user });
             Ok(())
         }
 
@@ -171,20 +360,127 @@ mod additional {
             total_coinday: u128, 
             timestamp: u128
         ) -> Result<()> {
-            self.only_owner()?;
+            let writer = self.only_writer(Scope::AwardPublisher)?;
             let new_award = Award {
                 amount,
                 total_coinday,
                 timestamp,
             };
-            self.awards.push(new_award);
+            self.ledger.push_award(new_award);
+            self.env().emit_event(AwardPublished {
+                writer,
+                index: self.ledger.awards_length() - 1,
+                amount,
+                total_coinday,
+                timestamp,
+            });
+            Ok(())
+        }
+
+        /// Fold at most `max_periods` old periods which every holder has collected into
+        /// the checkpoint. A holder left behind blocks the folding until it is settled, e.g. by
+        /// the keepers liquidating rewards in RELP. Only callable by award publishers.
+        /// Returns the number of folded periods.
+        #[ink(message)]
+        pub fn compact_awards(&mut self, max_periods: u32) -> Result<u32> {
+            let writer = self.only_writer(Scope::AwardPublisher)?;
+            let folded = self.ledger.compact(max_periods);
+            if folded > 0 {
+                self.env().emit_event(AwardsCompacted {
+                    writer,
+                    folded,
+                    checkpoint_index: self.ledger.checkpoint().index,
+                });
+            }
+            Ok(folded)
+        }
+
+        #[ink(message)]
+        pub fn vesting_config(&self) -> VestingConfig {
+            self.ledger.vesting_config()
+        }
+
+        /// Set the vesting schedule of claimed rewards, `enabled: false` pays them out directly.
+        #[ink(message)]
+        pub fn set_vesting_config(&mut self, config: VestingConfig) -> Result<()> {
+            self.only_owner()?;
+            if config.enabled && !config.is_valid() {
+                return Err(Error::InvalidVestingConfig)
+            }
+            self.ledger.set_vesting_config(config.clone());
+            self.env().emit_event(VestingConfigUpdated {
+                enabled: config.enabled,
+                cliff: config.cliff,
+                duration: config.duration,
+                penalty: config.penalty,
+            });
             Ok(())
         }
 
+        #[ink(message)]
+        pub fn vesting_of(&self, user: AccountId) -> Option<Vesting> {
+            self.ledger.vesting_of(&user)
+        }
+
+        /// Withdraw the vested part of the caller's position.
+        #[ink(message)]
+        pub fn withdraw_vested(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            let amount = self.ledger.withdraw_vested(&caller, now_time);
+            if amount == 0 {
+                return Err(Error::NothingToClaim)
+            }
+            self.pay(caller, amount);
+            self.env().emit_event(VestingWithdrawn { user: caller, amount });
+            Ok(amount)
+        }
+
+        /// Close the caller's position before its end. The unvested part is paid out minus
+        /// the exit penalty, which stays in the penalty pool to cover later vested rewards.
+        #[ink(message)]
+        pub fn exit_vesting(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            let (amount, penalty) = self.ledger.exit_vesting(&caller, now_time);
+            if amount + penalty == 0 {
+                return Err(Error::NothingToClaim)
+            }
+            self.penalty_pool += penalty;
+            self.pay(caller, amount);
+            self.env().emit_event(VestingExited { user: caller, amount, penalty });
+            Ok(amount)
+        }
+
+        /// ELC of exit penalties held to cover later vested rewards.
+        #[ink(message)]
+        pub fn penalty_pool(&self) -> u128 {
+            self.penalty_pool
+        }
+
+        /// Lock the collected ELC reward of `user` into its vesting position, called by RELP
+        /// when vesting is enabled. Returns the part covered by the penalty pool, RELP mints
+        /// the rest to this contract.
+        #[ink(message)]
+        pub fn vest(&mut self, user: AccountId, amount: u128) -> Result<u128> {
+            self.only_writer(Scope::RewardCrediter)?;
+            let from_penalty_pool = core::cmp::min(self.penalty_pool, amount);
+            self.penalty_pool -= from_penalty_pool;
+            let now_time = self.env().block_timestamp().into();
+            let released = self.ledger.vest(user, amount, now_time);
+            if released > 0 {
+                self.pay(user, released);
+            }
+            self.env().emit_event(Vested { user, amount, from_penalty_pool });
+            Ok(from_penalty_pool)
+        }
+
         #[ink(message)]
         pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
             self.only_owner()?;
+            let previous_owner = self.owner;
             self.owner = new_owner;
+            self.env().emit_event(OwnershipTransferred { previous_owner, new_owner });
             Ok(())
         }
 
@@ -200,6 +496,22 @@ mod additional {
             }
             Ok(())
         }
+
+        /// Panics on failure, so the ledger writes made before the payment are reverted.
+        fn pay(&mut self, user: AccountId, amount: Balance) {
+            if self.elc_contract.transfer(user, amount).is_err() {
+                panic!("ELC transfer failed")
+            }
+        }
+
+        /// Returns the caller if it is allowed to write in `scope`.
+        fn only_writer(&self, scope: Scope) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_writer(caller, scope) {
+                return Err(Error::OnlyWriterAccess)
+            }
+            Ok(caller)
+        }
     }
 
     /// Unit tests.
@@ -217,9 +529,21 @@ mod additional {
             test::default_accounts().expect("Cannot get accounts")
         }
 
+        fn set_caller(caller: AccountId) {
+            let callee = account_id::<DefaultEnvironment>();
+            let data = test::CallData::new(call::Selector::new([0x00; 4]));
+            test::push_execution_context::<DefaultEnvironment>(
+                caller,
+                callee,
+                100000,
+                100000,
+                data,
+            );
+        }
+
         #[ink::test]
         fn new_works() {
-            let additional = Additional::new(); 
+            let additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert_eq!(additional.total_reward(), 0);
             assert_eq!(additional.reward_of(accounts.alice), 0);
@@ -229,23 +553,23 @@ mod additional {
 
         #[ink::test]
         fn update_total_reward_works() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             assert!(additional.update_total_reward(1020).is_ok());
             assert_eq!(additional.total_reward(), 1020);
         }
 
         #[ink::test]
         fn update_total_reward_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(additional.update_total_reward(20), Err(Error::OnlyOwnerAccess));
+            assert_eq!(additional.update_total_reward(20), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
         fn update_rewards_works() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.update_rewards(accounts.alice, 200).is_ok());
             assert_eq!(additional.reward_of(accounts.alice), 200);
@@ -253,32 +577,32 @@ mod additional {
 
         #[ink::test]
         fn update_rewards_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(additional.update_rewards(accounts.alice, 20), Err(Error::OnlyOwnerAccess));
+            assert_eq!(additional.update_rewards(accounts.alice, 20), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
         fn update_total_coinday_works() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             assert!(additional.update_total_coinday((10, 20)).is_ok());
             assert_eq!(additional.total_coinday(), (10, 20));
         }
 
         #[ink::test]
         fn update_total_coinday_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(additional.update_total_coinday((10, 20)), Err(Error::OnlyOwnerAccess));
+            assert_eq!(additional.update_total_coinday((10, 20)), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
         fn update_coindays_works() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.update_coindays(accounts.alice, 66, 1000, 3).is_ok());
             let coinday_info = Coinday {amount: 66, timestamp: 1000, last_index: 3};
@@ -287,15 +611,15 @@ mod additional {
 
         #[ink::test]
         fn update_coindays_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
-            assert_eq!(additional.update_coindays(accounts.bob, 66, 10, 0), Err(Error::OnlyOwnerAccess));
+            assert_eq!(additional.update_coindays(accounts.bob, 66, 10, 0), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
         fn update_awards_works() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             assert!(additional.update_awards(10, 33, 66).is_ok());
             let award = Award {amount: 10, total_coinday: 33, timestamp: 66};
             assert_eq!(additional.get_award(0), award);
@@ -303,16 +627,158 @@ mod additional {
 
         #[ink::test]
         fn update_awards_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
-            assert_eq!(additional.update_awards(10, 33, 166600), Err(Error::OnlyOwnerAccess));
+            assert_eq!(additional.update_awards(10, 33, 166600), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
-        fn transfer_ownership_works() {
+        fn compact_awards_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(additional.update_awards(10, 100, 1).is_ok());
+            assert!(additional.update_awards(20, 200, 2).is_ok());
+            assert!(additional.update_awards(30, 300, 3).is_ok());
+            // alice still has to collect period 1
+            assert!(additional.update_coindays(accounts.alice, 66, 1, 1).is_ok());
+            assert_eq!(additional.compact_awards(10), Ok(1));
+            let checkpoint = Checkpoint {index: 1, timestamp: 1};
+            assert_eq!(additional.checkpoint(), checkpoint);
+            assert_eq!(additional.get_award(1), Award {amount: 20, total_coinday: 200, timestamp: 2});
+            assert_eq!(additional.try_get_award(0), None);
+
+            // alice collected all periods, the rest can be folded.
+            assert!(additional.update_coindays(accounts.alice, 66, 3, 3).is_ok());
+            assert_eq!(additional.compact_awards(10), Ok(2));
+            let checkpoint = Checkpoint {index: 3, timestamp: 3};
+            assert_eq!(additional.checkpoint(), checkpoint);
+            assert_eq!(additional.awards_length(), 3);
+            assert_eq!(additional.compact_awards(10), Ok(0));
+        }
+
+        #[ink::test]
+        fn compact_awards_respects_max_periods() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            assert!(additional.update_awards(10, 100, 1).is_ok());
+            assert!(additional.update_awards(20, 200, 2).is_ok());
+            assert_eq!(additional.compact_awards(1), Ok(1));
+            assert_eq!(additional.checkpoint().index, 1);
+            assert_eq!(additional.compact_awards(1), Ok(1));
+            assert_eq!(additional.checkpoint().index, 2);
+        }
+
+        #[ink::test]
+        fn compact_awards_failed_when_not_publisher() {
             let mut additional = Additional::new();
             let accounts = default_accounts();
+            assert!(additional.update_awards(10, 100, 1).is_ok());
+            set_caller(accounts.bob);
+            assert_eq!(additional.compact_awards(10), Err(Error::OnlyWriterAccess));
+            assert_eq!(additional.checkpoint().index, 0);
+        }
+
+        #[ink::test]
+        fn update_coindays_failed_when_period_compacted() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(additional.update_awards(10, 100, 1).is_ok());
+            assert_eq!(additional.compact_awards(10), Ok(1));
+            assert_eq!(additional.update_coindays(accounts.alice, 66, 1, 0), Err(Error::PeriodCompacted));
+            assert!(additional.update_coindays(accounts.alice, 66, 1, 1).is_ok());
+        }
+
+        #[ink::test]
+        fn remove_coindays_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(additional.update_awards(10, 100, 1).is_ok());
+            assert!(additional.update_coindays(accounts.bob, 66, 1, 0).is_ok());
+            assert_eq!(additional.compact_awards(10), Ok(0));
+            assert!(additional.remove_coindays(accounts.bob).is_ok());
+            assert_eq!(additional.get_coinday_info(accounts.bob).last_index, 1);
+            assert_eq!(additional.compact_awards(10), Ok(1));
+        }
+
+        #[ink::test]
+        fn grant_writer_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(!additional.is_writer(accounts.bob, Scope::CoindayUpdater));
+            assert!(additional.grant_writer(accounts.bob, Scope::CoindayUpdater).is_ok());
+            assert!(additional.is_writer(accounts.bob, Scope::CoindayUpdater));
+            assert!(!additional.is_writer(accounts.bob, Scope::RewardCrediter));
+
+            // bob writes in its own scope only.
+            set_caller(accounts.bob);
+            assert!(additional.update_coindays(accounts.charlie, 66, 1000, 3).is_ok());
+            assert!(additional.update_total_coinday((10, 20)).is_ok());
+            assert_eq!(additional.update_rewards(accounts.charlie, 20), Err(Error::OnlyWriterAccess));
+            assert_eq!(additional.update_awards(10, 33, 66), Err(Error::OnlyWriterAccess));
+        }
+
+        #[ink::test]
+        fn grant_writer_failed() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            set_caller(accounts.bob);
+            assert_eq!(additional.grant_writer(accounts.bob, Scope::AwardPublisher), Err(Error::OnlyOwnerAccess));
+        }
+
+        #[ink::test]
+        fn revoke_writer_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(additional.grant_writer(accounts.bob, Scope::AwardPublisher).is_ok());
+            assert!(additional.grant_writer(accounts.bob, Scope::RewardCrediter).is_ok());
+            assert!(additional.revoke_writer(accounts.bob, Scope::AwardPublisher).is_ok());
+            assert!(!additional.is_writer(accounts.bob, Scope::AwardPublisher));
+            assert!(additional.is_writer(accounts.bob, Scope::RewardCrediter));
+
+            set_caller(accounts.bob);
+            assert_eq!(additional.update_awards(10, 33, 66), Err(Error::OnlyWriterAccess));
+            assert!(additional.update_rewards(accounts.charlie, 20).is_ok());
+        }
+
+        fn vesting_config() -> VestingConfig {
+            VestingConfig { enabled: true, cliff: 10, duration: 100, penalty: 5000 }
+        }
+
+        #[ink::test]
+        fn set_vesting_config_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            assert!(!additional.vesting_config().enabled);
+            assert!(additional.set_vesting_config(vesting_config()).is_ok());
+            assert_eq!(additional.vesting_config(), vesting_config());
+
+            let invalid = VestingConfig { enabled: true, cliff: 10, duration: 100, penalty: 10001 };
+            assert_eq!(additional.set_vesting_config(invalid), Err(Error::InvalidVestingConfig));
+        }
+
+        #[ink::test]
+        fn vest_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            assert!(additional.set_vesting_config(vesting_config()).is_ok());
+            assert_eq!(additional.vest(accounts.bob, 500), Ok(0));
+            let vesting = additional.vesting_of(accounts.bob).unwrap();
+            assert_eq!(vesting.amount, 500);
+            assert_eq!(vesting.withdrawn, 0);
+            assert_eq!(additional.penalty_pool(), 0);
+        }
+
+        #[ink::test]
+        fn vest_failed() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
+            set_caller(accounts.bob);
+            assert_eq!(additional.vest(accounts.bob, 500), Err(Error::OnlyWriterAccess));
+        }
+
+        #[ink::test]
+        fn transfer_ownership_works() {
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
+            let accounts = default_accounts();
             assert_eq!(additional.owner(), accounts.alice);
             assert!(additional.transfer_ownership(accounts.bob).is_ok());
             assert_eq!(additional.owner(), accounts.bob);
@@ -320,7 +786,7 @@ mod additional {
 
         #[ink::test]
         fn transfer_ownership_failed() {
-            let mut additional = Additional::new();
+            let mut additional = Additional::new(AccountId::from([0x1; 32]));
             let accounts = default_accounts();
             
             // set bob as caller.
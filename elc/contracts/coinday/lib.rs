@@ -0,0 +1,823 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Coinday distribution shared by the reward streams of rELP holders.
+//!
+//! A stream publishes periodic awards which are split between holders by
+//! their coinday (balance multiplied by holding time). `Ledger` is the storage
+//! of one stream, embedded by the `reward` (ELP block award) and `additional`
+//! (ELC additional issuance) contracts. `Distribution` holds the math and works
+//! on any `CoindayLedger`, which is implemented by the references of both.
+//!
+//! Periods are keyed by index. Once no holder has a period left to collect it
+//! can be folded into the `Checkpoint`, which keeps the storage of a stream
+//! bounded by the periods still pending instead of its whole history.
+//!
+//! Claimed rewards can be locked into a linear `Vesting` with a cliff, an
+//! early exit pays a penalty which is published back to the stream.
+
+use core::marker::PhantomData;
+use ink_env::AccountId;
+use primitive_types::U256;
+use ink_storage::{
+    collections::HashMap as StorageHashMap,
+    traits::{PackedLayout, SpreadLayout},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Coinday {
+    // coinday of user
+    pub amount: u128,
+    // last time for update coinday
+    pub timestamp: u128,
+    // awards' index: record the index of award already got.
+    pub last_index: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Award {
+    // award amount of the period
+    pub amount: u128,
+    // total coinday at current timestamp
+    pub total_coinday: u128,
+    // current timestamp
+    pub timestamp: u128,
+}
+
+/// Fully settled periods folded together.
+#[derive(Clone, Debug, Default, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+pub struct Checkpoint {
+    // index of the first period not folded yet
+    pub index: u32,
+    // timestamp of the last folded period
+    pub timestamp: u128,
+}
+
+/// Vesting schedule applied to claimed rewards, durations in milliseconds.
+#[derive(Clone, Debug, Default, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+pub struct VestingConfig {
+    // claimed rewards are vested instead of paid out
+    pub enabled: bool,
+    // nothing vests before the cliff
+    pub cliff: u128,
+    // everything is vested after the duration
+    pub duration: u128,
+    // penalty on the unvested part for an early exit, base is 10000
+    pub penalty: u128,
+}
+
+impl VestingConfig {
+    pub fn is_valid(&self) -> bool {
+        self.duration > 0 && self.cliff <= self.duration && self.penalty <= 10000
+    }
+}
+
+/// Linear vesting position of a user.
+#[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Vesting {
+    // total amount of the position
+    pub amount: u128,
+    // amount already withdrawn
+    pub withdrawn: u128,
+    pub start: u128,
+    pub cliff: u128,
+    pub end: u128,
+}
+
+impl Vesting {
+    pub fn new(amount: u128, now_time: u128, config: &VestingConfig) -> Self {
+        Self {
+            amount,
+            withdrawn: 0,
+            start: now_time,
+            cliff: now_time + config.cliff,
+            end: now_time + config.duration,
+        }
+    }
+
+    /// Vested amount at `now_time`, withdrawn part included.
+    pub fn vested(&self, now_time: u128) -> u128 {
+        if now_time < self.cliff {
+            0
+        } else if now_time >= self.end {
+            self.amount
+        } else {
+            self.amount * (now_time - self.start) / (self.end - self.start)
+        }
+    }
+
+    pub fn withdrawable(&self, now_time: u128) -> u128 {
+        self.vested(now_time) - self.withdrawn
+    }
+
+    pub fn locked(&self, now_time: u128) -> u128 {
+        self.amount - self.vested(now_time)
+    }
+}
+
+/// Write scopes of a stream, granted by its owner to writer contracts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Scope {
+    /// Publish awards, total reward and daily award.
+    AwardPublisher,
+    /// Update coindays of users and the total coinday.
+    CoindayUpdater,
+    /// Credit rewards to users.
+    RewardCrediter,
+}
+
+impl Scope {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Asset paid out by a reward stream.
+pub trait RewardAsset {
+    /// Multiplier applied to every period share.
+    const SCALE: u128;
+}
+
+/// ELP block award, the daily award is already scaled by 1e8.
+pub enum Elp {}
+
+impl RewardAsset for Elp {
+    const SCALE: u128 = 1;
+}
+
+/// ELC additional issuance, shares are scaled by 1e8.
+pub enum Elc {}
+
+impl RewardAsset for Elc {
+    const SCALE: u128 = 1e8 as u128;
+}
+
+/// Storage of one reward stream.
+#[derive(SpreadLayout)]
+#[cfg_attr(feature = "std", derive(ink_storage::traits::StorageLayout))]
+pub struct Ledger {
+    /// Total reward.
+    total_reward: u128,
+    /// Mapping from owner to reward owned.
+    rewards: StorageHashMap<AccountId, u128>,
+    /// Total coinday (total_coinday, last_update_time)
+    total_coinday: (u128, u128),
+    /// Mapping from owner to a tuple (coinday, last_update_time)
+    coindays: StorageHashMap<AccountId, Coinday>,
+    /// Mapping from period index to award info, folded periods are removed.
+    awards: StorageHashMap<u32, Award>,
+    /// Number of periods published.
+    awards_length: u32,
+    /// Periods folded so far.
+    checkpoint: Checkpoint,
+    /// Mapping from period index to the number of holders whose next period to collect is it.
+    holders: StorageHashMap<u32, u32>,
+    /// Mapping from writer to its granted scopes, one bit per `Scope`.
+    writers: StorageHashMap<AccountId, u8>,
+    /// Vesting schedule of claimed rewards.
+    vesting_config: VestingConfig,
+    /// Mapping from user to its vesting position.
+    vestings: StorageHashMap<AccountId, Vesting>,
+}
+
+impl Ledger {
+    pub fn new(now_time: u128) -> Self {
+        Self {
+            total_reward: 0,
+            rewards: StorageHashMap::new(),
+            total_coinday: (0, now_time),
+            coindays: StorageHashMap::new(),
+            awards: StorageHashMap::new(),
+            awards_length: 0,
+            checkpoint: Default::default(),
+            holders: StorageHashMap::new(),
+            writers: StorageHashMap::new(),
+            vesting_config: Default::default(),
+            vestings: StorageHashMap::new(),
+        }
+    }
+
+    pub fn total_reward(&self) -> u128 {
+        self.total_reward
+    }
+
+    pub fn reward_of(&self, user: &AccountId) -> u128 {
+        self.rewards.get(user).copied().unwrap_or(0)
+    }
+
+    pub fn total_coinday(&self) -> (u128, u128) {
+        self.total_coinday
+    }
+
+    /// Award of period `index`, `None` if not published or already folded.
+    pub fn get_award(&self, index: u32) -> Option<Award> {
+        self.awards.get(&index).cloned()
+    }
+
+    pub fn awards_length(&self) -> u32 {
+        self.awards_length
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint.clone()
+    }
+
+    /// Coinday info of `user`, an empty record starting at `now_time` if none.
+    /// A user without record has nothing to collect from the published periods.
+    pub fn get_coinday_info(&self, user: &AccountId, now_time: u128) -> Coinday {
+        self.coindays.get(user).cloned().unwrap_or(Coinday {
+            amount: 0,
+            timestamp: now_time,
+            last_index: self.awards_length,
+        })
+    }
+
+    pub fn set_total_reward(&mut self, new_value: u128) {
+        self.total_reward = new_value;
+    }
+
+    pub fn set_reward(&mut self, user: AccountId, value: u128) {
+        self.rewards.insert(user, value);
+    }
+
+    pub fn set_total_coinday(&mut self, new_value: (u128, u128)) {
+        self.total_coinday = new_value;
+    }
+
+    pub fn set_coinday(&mut self, user: AccountId, info: Coinday) {
+        if let Some(old_index) = self.coindays.get(&user).map(|c| c.last_index) {
+            self.leave_period(old_index);
+        }
+        self.enter_period(info.last_index);
+        self.coindays.insert(user, info);
+    }
+
+    /// Drop the record of a user who holds nothing anymore.
+    pub fn remove_coinday(&mut self, user: &AccountId) {
+        if let Some(old) = self.coindays.take(user) {
+            self.leave_period(old.last_index);
+        }
+    }
+
+    pub fn push_award(&mut self, award: Award) {
+        self.awards.insert(self.awards_length, award);
+        self.awards_length += 1;
+    }
+
+    /// Fold at most `max_periods` fully settled periods into the checkpoint.
+    /// A period is fully settled once no holder has it left to collect.
+    /// Returns the number of folded periods.
+    pub fn compact(&mut self, max_periods: u32) -> u32 {
+        let mut folded = 0;
+        while folded < max_periods && self.checkpoint.index < self.awards_length {
+            let index = self.checkpoint.index;
+            // holders before the checkpoint are not allowed, so only this period needs a check.
+            if self.holders.get(&index).copied().unwrap_or(0) > 0 {
+                break
+            }
+            let award = match self.awards.take(&index) {
+                Some(award) => award,
+                None => break,
+            };
+            self.checkpoint = Checkpoint {
+                index: index + 1,
+                timestamp: award.timestamp,
+            };
+            folded += 1;
+        }
+        folded
+    }
+
+    fn enter_period(&mut self, index: u32) {
+        let count = self.holders.get(&index).copied().unwrap_or(0);
+        self.holders.insert(index, count + 1);
+    }
+
+    fn leave_period(&mut self, index: u32) {
+        match self.holders.get(&index).copied() {
+            Some(count) if count > 1 => {
+                self.holders.insert(index, count - 1);
+            }
+            _ => {
+                self.holders.take(&index);
+            }
+        }
+    }
+
+    pub fn vesting_config(&self) -> VestingConfig {
+        self.vesting_config.clone()
+    }
+
+    pub fn set_vesting_config(&mut self, config: VestingConfig) {
+        self.vesting_config = config;
+    }
+
+    pub fn vesting_of(&self, user: &AccountId) -> Option<Vesting> {
+        self.vestings.get(user).cloned()
+    }
+
+    /// Lock `amount` for `user` under the current schedule. The locked part of an existing
+    /// position is merged into the new one and its vested part released.
+    /// Returns the released amount to pay out now.
+    pub fn vest(&mut self, user: AccountId, amount: u128, now_time: u128) -> u128 {
+        let (released, locked) = match self.vestings.get(&user) {
+            Some(old) => (old.withdrawable(now_time), old.locked(now_time)),
+            None => (0, 0),
+        };
+        let vesting = Vesting::new(locked + amount, now_time, &self.vesting_config);
+        self.vestings.insert(user, vesting);
+        released
+    }
+
+    /// Withdraw the vested part of the position of `user`. Returns the withdrawn amount.
+    pub fn withdraw_vested(&mut self, user: &AccountId, now_time: u128) -> u128 {
+        let mut vesting = match self.vestings.get(user) {
+            Some(vesting) => vesting.clone(),
+            None => return 0,
+        };
+        let amount = vesting.withdrawable(now_time);
+        vesting.withdrawn += amount;
+        if vesting.withdrawn == vesting.amount {
+            self.vestings.take(user);
+        } else {
+            self.vestings.insert(*user, vesting);
+        }
+        amount
+    }
+
+    /// Close the position of `user` before its end.
+    /// Returns the amount to pay out and the penalty taken from the locked part.
+    pub fn exit_vesting(&mut self, user: &AccountId, now_time: u128) -> (u128, u128) {
+        let vesting = match self.vestings.take(user) {
+            Some(vesting) => vesting,
+            None => return (0, 0),
+        };
+        let locked = vesting.locked(now_time);
+        let penalty = locked * self.vesting_config.penalty / 10000;
+        (vesting.withdrawable(now_time) + locked - penalty, penalty)
+    }
+
+    /// Publish `amount` of asset `A` taken from `user` to the other holders as a new period
+    /// at `now_time`, split by `total_coinday`, the total coinday at `now_time` without the
+    /// coinday of `user`. `user` must have collected every published period, it is moved past
+    /// the new one. Returns false if there is no coinday to split it by.
+    pub fn redistribute<A: RewardAsset>(
+        &mut self,
+        user: &AccountId,
+        amount: u128,
+        total_coinday: u128,
+        now_time: u128,
+    ) -> bool {
+        if total_coinday == 0 || amount / A::SCALE == 0 {
+            return false
+        }
+        self.total_reward += amount / A::SCALE;
+        self.push_award(Award {
+            amount: amount / A::SCALE,
+            total_coinday,
+            timestamp: now_time,
+        });
+        // 被罚者不参与分配自己的罚金
+        if let Some(mut info) = self.coindays.get(user).cloned() {
+            info.last_index = self.awards_length;
+            self.set_coinday(*user, info);
+        }
+        true
+    }
+
+    pub fn has_scope(&self, writer: &AccountId, scope: Scope) -> bool {
+        self.writers.get(writer).copied().unwrap_or(0) & scope.bit() != 0
+    }
+
+    pub fn set_scope(&mut self, writer: AccountId, scope: Scope, allowed: bool) {
+        let scopes = self.writers.get(&writer).copied().unwrap_or(0);
+        let scopes = if allowed { scopes | scope.bit() } else { scopes & !scope.bit() };
+        if scopes == 0 {
+            self.writers.take(&writer);
+        } else {
+            self.writers.insert(writer, scopes);
+        }
+    }
+}
+
+/// Read and write access to a reward stream, used by `Distribution`.
+pub trait CoindayLedger {
+    fn coinday_of(&self, user: AccountId) -> Coinday;
+    fn award_at(&self, index: u32) -> Option<Award>;
+    fn awards_len(&self) -> u32;
+    fn stream_coinday(&self) -> (u128, u128);
+    fn stream_reward(&self) -> u128;
+    fn reward_balance(&self, user: AccountId) -> u128;
+    fn set_coinday(&mut self, user: AccountId, info: Coinday);
+    fn remove_coinday(&mut self, user: AccountId);
+    fn set_stream_coinday(&mut self, new_value: (u128, u128));
+    fn set_stream_reward(&mut self, new_value: u128);
+    fn set_reward_balance(&mut self, user: AccountId, value: u128);
+    fn push_award(&mut self, award: Award);
+}
+
+/// Coinday math of a reward stream paying out asset `A`.
+pub struct Distribution<A>(PhantomData<A>);
+
+impl<A: RewardAsset> Distribution<A> {
+    /// Coinday of a holder accrued up to `now_time`.
+    pub fn accrue(info: &Coinday, balance: u128, now_time: u128) -> Result<u128, MathError> {
+        let elapsed = sub(now_time, info.timestamp)?;
+        add(info.amount, mul(balance, elapsed)?)
+    }
+
+    /// Share of `award` owned by a holder.
+    pub fn award_share(info: &Coinday, balance: u128, award: &Award) -> Result<u128, MathError> {
+        if award.total_coinday == 0 {
+            return Ok(0)
+        }
+        // 计算截止该期奖励时间点，用户的币天数
+        let coinday = Self::accrue(info, balance, award.timestamp)?;
+        mul_div(coinday, mul(award.amount, A::SCALE)?, award.total_coinday)
+    }
+
+    /// Coinday leaving the account together with `value` out of `balance`.
+    pub fn decrease_of(coinday: u128, balance: u128, value: u128) -> Result<u128, MathError> {
+        if balance == 0 {
+            return Ok(0)
+        }
+        // decrease amount = coinday of user * ( value / balance );
+        mul_div(coinday, value, balance)
+    }
+
+    /// Sum of the shares of periods `[from, to)`.
+    pub fn pending<L: CoindayLedger>(
+        ledger: &L,
+        info: &Coinday,
+        balance: u128,
+        from: u32,
+        to: u32,
+    ) -> Result<u128, MathError> {
+        let mut amount = 0;
+        for i in from..to {
+            // 有持有者未领取的期数不会被折叠，取不到说明账本已损坏
+            let award = ledger.award_at(i).expect("Uncollected period was folded");
+            amount = add(amount, Self::award_share(info, balance, &award)?)?;
+        }
+        Ok(amount)
+    }
+
+    /// Settle at most `max_periods` uncollected periods of `user` into its reward,
+    /// moving its coinday to the last settled period.
+    /// Returns the settled amount and the index of the next uncollected period.
+    pub fn settle<L: CoindayLedger>(
+        ledger: &mut L,
+        user: AccountId,
+        balance: u128,
+        max_periods: u32,
+    ) -> Result<(u128, u32), MathError> {
+        let info = ledger.coinday_of(user);
+        let length = ledger.awards_len();
+        let end = core::cmp::min(length, info.last_index.saturating_add(max_periods));
+        if end <= info.last_index {
+            return Ok((0, info.last_index))
+        }
+
+        let amount = Self::pending(ledger, &info, balance, info.last_index, end)?;
+        if amount > 0 {
+            let old_reward = ledger.reward_balance(user);
+            ledger.set_reward_balance(user, add(old_reward, amount)?);
+        }
+        let timestamp = ledger.award_at(end - 1).map_or(info.timestamp, |award| award.timestamp);
+        let new_info = Coinday {
+            amount: Self::accrue(&info, balance, timestamp)?,
+            timestamp,
+            last_index: end,
+        };
+        ledger.set_coinday(user, new_info);
+        Ok((amount, end))
+    }
+
+    /// Accrue the coinday of `user` to `now_time` and mark periods before `index` as collected.
+    pub fn increase<L: CoindayLedger>(
+        ledger: &mut L,
+        user: AccountId,
+        balance: u128,
+        now_time: u128,
+        index: u32,
+    ) -> Result<(), MathError> {
+        let info = ledger.coinday_of(user);
+        let new_info = Coinday {
+            amount: Self::accrue(&info, balance, now_time)?,
+            timestamp: now_time,
+            last_index: index,
+        };
+        ledger.set_coinday(user, new_info);
+        Ok(())
+    }
+
+    /// Accrue the coinday of `user` to `now_time` and remove the part of `value`,
+    /// the record is dropped once the whole balance leaves. Returns the removed coinday.
+    pub fn decrease<L: CoindayLedger>(
+        ledger: &mut L,
+        user: AccountId,
+        balance: u128,
+        value: u128,
+        now_time: u128,
+        index: u32,
+    ) -> Result<u128, MathError> {
+        let info = ledger.coinday_of(user);
+        // 先将币天更新到当前时间点
+        let cur_coinday = Self::accrue(&info, balance, now_time)?;
+        if value >= balance {
+            ledger.remove_coinday(user);
+            return Ok(cur_coinday)
+        }
+        let decrease_coinday = Self::decrease_of(cur_coinday, balance, value)?;
+        let new_info = Coinday {
+            amount: sub(cur_coinday, decrease_coinday)?,
+            timestamp: now_time,
+            last_index: index,
+        };
+        ledger.set_coinday(user, new_info);
+        Ok(decrease_coinday)
+    }
+
+    /// Accrue the total coinday to `now_time` and remove `decrease`.
+    pub fn update_total<L: CoindayLedger>(
+        ledger: &mut L,
+        total_supply: u128,
+        now_time: u128,
+        decrease: u128,
+    ) -> Result<u128, MathError> {
+        let (cur_total_coinday, last_time) = ledger.stream_coinday();
+        let increase_coinday = mul(total_supply, sub(now_time, last_time)?)?;
+        let new_total_coinday = sub(add(cur_total_coinday, increase_coinday)?, decrease)?;
+        ledger.set_stream_coinday((new_total_coinday, now_time));
+        Ok(new_total_coinday)
+    }
+
+    /// Publish a new period of `amount` at `now_time`.
+    /// Returns the total coinday the period is split by.
+    pub fn publish<L: CoindayLedger>(
+        ledger: &mut L,
+        amount: u128,
+        total_supply: u128,
+        now_time: u128,
+    ) -> Result<u128, MathError> {
+        let total_coinday = Self::update_total(ledger, total_supply, now_time, 0)?;
+        let old_total_reward = ledger.stream_reward();
+        ledger.set_stream_reward(add(old_total_reward, amount)?);
+        ledger.push_award(Award {
+            amount,
+            total_coinday,
+            timestamp: now_time,
+        });
+        Ok(total_coinday)
+    }
+}
+
+/// Arithmetic errors of the coinday math, which would wrap silently with overflow checks off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+    Underflow,
+}
+
+pub fn add(a: u128, b: u128) -> Result<u128, MathError> {
+    a.checked_add(b).ok_or(MathError::Overflow)
+}
+
+pub fn sub(a: u128, b: u128) -> Result<u128, MathError> {
+    a.checked_sub(b).ok_or(MathError::Underflow)
+}
+
+pub fn mul(a: u128, b: u128) -> Result<u128, MathError> {
+    a.checked_mul(b).ok_or(MathError::Overflow)
+}
+
+/// `a * b / c` with the product widened to 256 bits, `c` must not be zero.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Result<u128, MathError> {
+    let result = U256::from(a) * U256::from(b) / U256::from(c);
+    if result > U256::from(u128::MAX) {
+        return Err(MathError::Overflow)
+    }
+    Ok(result.as_u128())
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    /// Imports all the definitions from the outer scope so we can use them here.
+    use super::*;
+    use ink_prelude::{collections::BTreeMap, vec::Vec};
+
+    /// In-memory stream, stands in for a reward contract reference.
+    #[derive(Default)]
+    struct MockLedger {
+        total_reward: u128,
+        rewards: BTreeMap<AccountId, u128>,
+        total_coinday: (u128, u128),
+        coindays: BTreeMap<AccountId, Coinday>,
+        awards: Vec<Award>,
+    }
+
+    impl CoindayLedger for MockLedger {
+        fn coinday_of(&self, user: AccountId) -> Coinday {
+            self.coindays.get(&user).cloned().unwrap_or(Coinday {
+                amount: 0,
+                timestamp: 0,
+                last_index: 0,
+            })
+        }
+        fn award_at(&self, index: u32) -> Option<Award> {
+            self.awards.get(index as usize).cloned()
+        }
+        fn awards_len(&self) -> u32 {
+            self.awards.len() as u32
+        }
+        fn stream_coinday(&self) -> (u128, u128) {
+            self.total_coinday
+        }
+        fn stream_reward(&self) -> u128 {
+            self.total_reward
+        }
+        fn reward_balance(&self, user: AccountId) -> u128 {
+            self.rewards.get(&user).copied().unwrap_or(0)
+        }
+        fn set_coinday(&mut self, user: AccountId, info: Coinday) {
+            self.coindays.insert(user, info);
+        }
+        fn remove_coinday(&mut self, user: AccountId) {
+            self.coindays.remove(&user);
+        }
+        fn set_stream_coinday(&mut self, new_value: (u128, u128)) {
+            self.total_coinday = new_value;
+        }
+        fn set_stream_reward(&mut self, new_value: u128) {
+            self.total_reward = new_value;
+        }
+        fn set_reward_balance(&mut self, user: AccountId, value: u128) {
+            self.rewards.insert(user, value);
+        }
+        fn push_award(&mut self, award: Award) {
+            self.awards.push(award);
+        }
+    }
+
+    fn alice() -> AccountId {
+        AccountId::from([0x1; 32])
+    }
+
+    fn bob() -> AccountId {
+        AccountId::from([0x2; 32])
+    }
+
+    #[test]
+    fn vesting_is_linear_after_cliff() {
+        let config = VestingConfig { enabled: true, cliff: 20, duration: 100, penalty: 5000 };
+        let vesting = Vesting::new(1000, 10, &config);
+        assert_eq!(vesting.vested(29), 0);
+        assert_eq!(vesting.vested(30), 200);
+        assert_eq!(vesting.vested(60), 500);
+        assert_eq!(vesting.vested(110), 1000);
+        assert_eq!(vesting.vested(200), 1000);
+        assert_eq!(vesting.locked(60), 500);
+    }
+
+    #[test]
+    fn vesting_withdrawable_excludes_withdrawn() {
+        let config = VestingConfig { enabled: true, cliff: 0, duration: 100, penalty: 0 };
+        let mut vesting = Vesting::new(1000, 0, &config);
+        vesting.withdrawn = 300;
+        assert_eq!(vesting.withdrawable(50), 200);
+        assert_eq!(vesting.withdrawable(100), 700);
+    }
+
+    #[test]
+    fn vesting_config_validation() {
+        assert!(VestingConfig { enabled: true, cliff: 10, duration: 100, penalty: 10000 }.is_valid());
+        assert!(!VestingConfig { enabled: true, cliff: 10, duration: 0, penalty: 0 }.is_valid());
+        assert!(!VestingConfig { enabled: true, cliff: 101, duration: 100, penalty: 0 }.is_valid());
+        assert!(!VestingConfig { enabled: true, cliff: 10, duration: 100, penalty: 10001 }.is_valid());
+    }
+
+    #[test]
+    fn accrue_works() {
+        let info = Coinday { amount: 10, timestamp: 100, last_index: 0 };
+        assert_eq!(Distribution::<Elp>::accrue(&info, 5, 100), Ok(10));
+        assert_eq!(Distribution::<Elp>::accrue(&info, 5, 120), Ok(110));
+    }
+
+    #[test]
+    fn award_share_is_scaled_by_asset() {
+        let info = Coinday { amount: 0, timestamp: 0, last_index: 0 };
+        let award = Award { amount: 1000, total_coinday: 4000, timestamp: 10 };
+        assert_eq!(Distribution::<Elp>::award_share(&info, 100, &award), Ok(250));
+        assert_eq!(Distribution::<Elc>::award_share(&info, 100, &award), Ok(250 * 1e8 as u128));
+    }
+
+    #[test]
+    fn award_share_of_empty_period_is_zero() {
+        let info = Coinday { amount: 0, timestamp: 0, last_index: 0 };
+        let award = Award { amount: 1000, total_coinday: 0, timestamp: 10 };
+        assert_eq!(Distribution::<Elp>::award_share(&info, 100, &award), Ok(0));
+    }
+
+    #[test]
+    fn decrease_of_partial_transfer() {
+        assert_eq!(Distribution::<Elp>::decrease_of(1000, 4, 1), Ok(250));
+        assert_eq!(Distribution::<Elp>::decrease_of(1000, 4, 4), Ok(1000));
+        assert_eq!(Distribution::<Elp>::decrease_of(1000, 0, 0), Ok(0));
+    }
+
+    #[test]
+    fn coinday_math_reports_overflow() {
+        let info = Coinday { amount: 0, timestamp: 100, last_index: 0 };
+        assert_eq!(Distribution::<Elp>::accrue(&info, 5, 99), Err(MathError::Underflow));
+        assert_eq!(Distribution::<Elp>::accrue(&info, u128::MAX, 102), Err(MathError::Overflow));
+        assert_eq!(sub(1, 2), Err(MathError::Underflow));
+        assert_eq!(add(u128::MAX, 1), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn award_share_is_widened() {
+        // realistic balance and millisecond timestamps, the product exceeds u128.
+        let info = Coinday { amount: 0, timestamp: 0, last_index: 0 };
+        let balance = 1_000_000 * 1e8 as u128;
+        let award = Award { amount: 1_000_000 * 1e8 as u128, total_coinday: balance * 4 * 1e10 as u128, timestamp: 1e10 as u128 };
+        assert_eq!(Distribution::<Elc>::award_share(&info, balance, &award), Ok(award.amount * 1e8 as u128 / 4));
+        assert_eq!(mul_div(u128::MAX, 2, 1), Err(MathError::Overflow));
+        assert_eq!(mul_div(u128::MAX, 4, 8), Ok(u128::MAX / 2));
+    }
+
+    #[test]
+    fn publish_accrues_total_coinday() {
+        let mut ledger = MockLedger::default();
+        assert_eq!(Distribution::<Elp>::publish(&mut ledger, 1000, 400, 10), Ok(4000));
+        assert_eq!(Distribution::<Elp>::publish(&mut ledger, 500, 400, 20), Ok(8000));
+        assert_eq!(ledger.stream_reward(), 1500);
+        assert_eq!(ledger.stream_coinday(), (8000, 20));
+        assert_eq!(ledger.award_at(1), Some(Award { amount: 500, total_coinday: 8000, timestamp: 20 }));
+    }
+
+    #[test]
+    fn settle_splits_period_by_coinday() {
+        let mut ledger = MockLedger::default();
+        Distribution::<Elp>::publish(&mut ledger, 1000, 400, 10).unwrap();
+        // alice holds 100 and bob 300 since 0.
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, alice(), 100, 50), Ok((250, 1)));
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, bob(), 300, 50), Ok((750, 1)));
+        assert_eq!(ledger.reward_balance(alice()), 250);
+        assert_eq!(ledger.coinday_of(alice()), Coinday { amount: 1000, timestamp: 10, last_index: 1 });
+        // nothing left to settle.
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, alice(), 100, 50), Ok((0, 1)));
+        assert_eq!(ledger.reward_balance(alice()), 250);
+    }
+
+    #[test]
+    fn settle_stops_at_period_boundary() {
+        let mut ledger = MockLedger::default();
+        Distribution::<Elp>::publish(&mut ledger, 1000, 100, 10).unwrap();
+        Distribution::<Elp>::publish(&mut ledger, 500, 100, 20).unwrap();
+        Distribution::<Elp>::publish(&mut ledger, 200, 100, 30).unwrap();
+        let all = Distribution::<Elp>::pending(&ledger, &ledger.coinday_of(alice()), 100, 0, 3).unwrap();
+        assert_eq!(all, 1700);
+
+        // settle two periods, the coinday moves to the end of the second one.
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, alice(), 100, 2), Ok((1500, 2)));
+        assert_eq!(ledger.coinday_of(alice()), Coinday { amount: 2000, timestamp: 20, last_index: 2 });
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, alice(), 100, 2), Ok((200, 3)));
+        assert_eq!(ledger.reward_balance(alice()), all);
+    }
+
+    #[test]
+    fn partial_transfer_moves_coinday() {
+        let mut ledger = MockLedger::default();
+        Distribution::<Elp>::publish(&mut ledger, 1000, 400, 10).unwrap();
+        Distribution::<Elp>::settle(&mut ledger, alice(), 100, 50).unwrap();
+        Distribution::<Elp>::settle(&mut ledger, bob(), 300, 50).unwrap();
+
+        // alice transfers 50 to bob at 10.
+        let decrease = Distribution::<Elp>::decrease(&mut ledger, alice(), 100, 50, 10, 1).unwrap();
+        assert_eq!(decrease, 500);
+        Distribution::<Elp>::increase(&mut ledger, bob(), 300, 10, 1).unwrap();
+        assert_eq!(Distribution::<Elp>::update_total(&mut ledger, 400, 10, decrease), Ok(3500));
+        assert_eq!(ledger.coinday_of(alice()), Coinday { amount: 500, timestamp: 10, last_index: 1 });
+        assert_eq!(ledger.coinday_of(bob()), Coinday { amount: 3000, timestamp: 10, last_index: 1 });
+
+        // next period is split by the new balances: 50 and 350.
+        assert_eq!(Distribution::<Elp>::publish(&mut ledger, 1000, 400, 20), Ok(7500));
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, alice(), 50, 50), Ok((133, 2)));
+        assert_eq!(Distribution::<Elp>::settle(&mut ledger, bob(), 350, 50), Ok((866, 2)));
+    }
+
+    #[test]
+    fn decrease_whole_balance_drops_record() {
+        let mut ledger = MockLedger::default();
+        Distribution::<Elp>::increase(&mut ledger, alice(), 0, 10, 0).unwrap();
+        assert!(ledger.coindays.contains_key(&alice()));
+        assert_eq!(Distribution::<Elp>::decrease(&mut ledger, alice(), 100, 100, 20, 0), Ok(1000));
+        assert!(!ledger.coindays.contains_key(&alice()));
+    }
+}
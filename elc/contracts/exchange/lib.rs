@@ -0,0 +1,249 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use self::exchange::{Exchange, ExchangeRef};
+use ink_lang as ink;
+/// Constant-product ELP/ELC pool, a mock of the exchange `Stable` trades on. Its messages
+/// are the swap adapter messages of `swap`, so `Stable` can call it directly.
+///
+/// ELP is the native token, ELC is pulled with `transfer_from` and must be approved
+/// first. Liquidity providers get pool shares, redeemable for their part of both reserves.
+#[ink::contract]
+mod exchange {
+    use elc::ELCRef;
+    use swap::{input_price, share_of, shares_for};
+    use ink_env::call::FromAccountId;
+    use ink_storage::{collections::HashMap as StorageHashMap, lazy::Lazy};
+
+    /// The Exchange error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        InvalidAmount,
+        InsufficientLiquidity,
+        TransferFailed,
+        InsufficientShares,
+        /// The trade pays out less than the `min_out` asked.
+        SlippageExceeded,
+    }
+
+    /// The Exchange result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    #[ink(storage)]
+    pub struct Exchange {
+        /// ELP in the pool.
+        elp_reserve: Balance,
+        /// ELC in the pool.
+        elc_reserve: Balance,
+        elc_contract: Lazy<ELCRef>,
+        /// Pool shares of each provider.
+        shares: StorageHashMap<AccountId, Balance>,
+        total_shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityAdded {
+        #[ink(topic)]
+        provider: AccountId,
+        elp_amount: Balance,
+        elc_amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityRemoved {
+        #[ink(topic)]
+        provider: AccountId,
+        elp_amount: Balance,
+        elc_amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Swap {
+        #[ink(topic)]
+        trader: AccountId,
+        elp_in: Balance,
+        elc_in: Balance,
+        elp_out: Balance,
+        elc_out: Balance,
+    }
+
+    impl Exchange {
+        #[ink(constructor)]
+        pub fn new(elc_token: AccountId) -> Self {
+            let elc_contract: ELCRef = FromAccountId::from_account_id(elc_token);
+            Self {
+                elp_reserve: 0,
+                elc_reserve: 0,
+                elc_contract: Lazy::new(elc_contract),
+                shares: StorageHashMap::new(),
+                total_shares: 0,
+            }
+        }
+
+        /// Add the ELP sent with the call and `elc_amount` ELC of the caller to the pool.
+        /// Returns the shares minted, see `swap::shares_for`.
+        #[ink(message, payable)]
+        pub fn add_liquidity(&mut self, elc_amount: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            let elp_amount = self.env().transferred_balance();
+            if elp_amount == 0 || elc_amount == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            let shares = shares_for(elp_amount, elc_amount, self.elp_reserve, self.elc_reserve, self.total_shares);
+            if shares == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            let exchange = self.env().account_id();
+            if self.elc_contract.transfer_from(caller, exchange, elc_amount).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            self.elp_reserve += elp_amount;
+            self.elc_reserve += elc_amount;
+            let owned = self.shares_of(caller);
+            self.shares.insert(caller, owned + shares);
+            self.total_shares += shares;
+            self.env().emit_event(LiquidityAdded { provider: caller, elp_amount, elc_amount, shares });
+            Ok(shares)
+        }
+
+        /// Burn `shares` of the caller for their part of both reserves. Returns `(elp, elc)`.
+        #[ink(message)]
+        pub fn remove_liquidity(&mut self, shares: Balance) -> Result<(Balance, Balance)> {
+            let caller = self.env().caller();
+            let owned = self.shares_of(caller);
+            if shares == 0 || shares > owned {
+                return Err(Error::InsufficientShares)
+            }
+            let elp_amount = share_of(shares, self.total_shares, self.elp_reserve);
+            let elc_amount = share_of(shares, self.total_shares, self.elc_reserve);
+            if self.elc_contract.transfer(caller, elc_amount).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            if self.env().transfer(caller, elp_amount).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            self.elp_reserve -= elp_amount;
+            self.elc_reserve -= elc_amount;
+            self.shares.insert(caller, owned - shares);
+            self.total_shares -= shares;
+            self.env().emit_event(LiquidityRemoved { provider: caller, elp_amount, elc_amount, shares });
+            Ok((elp_amount, elc_amount))
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, owner: AccountId) -> Balance {
+            self.shares.get(&owner).copied().unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// Returns `(elp_reserve, elc_reserve)`.
+        #[ink(message)]
+        pub fn reserves(&self) -> (Balance, Balance) {
+            (self.elp_reserve, self.elc_reserve)
+        }
+
+        #[ink(message)]
+        pub fn quote_elc_to_elp(&self, elc_in: Balance) -> Balance {
+            input_price(elc_in, self.elc_reserve, self.elp_reserve)
+        }
+
+        #[ink(message)]
+        pub fn quote_elp_to_elc(&self, elp_in: Balance) -> Balance {
+            input_price(elp_in, self.elp_reserve, self.elc_reserve)
+        }
+
+        /// Sell `elc_in` ELC of the caller for at least `min_out` ELP. Returns the ELP paid out.
+        #[ink(message)]
+        pub fn swap_elc_to_elp(&mut self, elc_in: Balance, min_out: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            let elp_out = self.quote_elc_to_elp(elc_in);
+            if elp_out == 0 {
+                return Err(Error::InsufficientLiquidity)
+            }
+            if elp_out < min_out {
+                return Err(Error::SlippageExceeded)
+            }
+            let exchange = self.env().account_id();
+            if self.elc_contract.transfer_from(caller, exchange, elc_in).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            if self.env().transfer(caller, elp_out).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            self.elc_reserve += elc_in;
+            self.elp_reserve -= elp_out;
+            self.env().emit_event(Swap { trader: caller, elp_in: 0, elc_in, elp_out, elc_out: 0 });
+            Ok(elp_out)
+        }
+
+        /// Sell the ELP sent with the call for at least `min_out` ELC. Returns the ELC paid out.
+        #[ink(message, payable)]
+        pub fn swap_elp_to_elc(&mut self, min_out: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            let elp_in = self.env().transferred_balance();
+            let elc_out = self.quote_elp_to_elc(elp_in);
+            if elc_out == 0 {
+                return Err(Error::InsufficientLiquidity)
+            }
+            if elc_out < min_out {
+                return Err(Error::SlippageExceeded)
+            }
+            if self.elc_contract.transfer(caller, elc_out).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            self.elp_reserve += elp_in;
+            self.elc_reserve -= elc_out;
+            self.env().emit_event(Swap { trader: caller, elp_in, elc_in: 0, elp_out: 0, elc_out });
+            Ok(elc_out)
+        }
+    }
+
+    /// Unit tests
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+        use ink_lang as ink;
+
+        fn new_exchange(elp_reserve: Balance, elc_reserve: Balance) -> Exchange {
+            let mut exchange = Exchange::new(AccountId::from([0x1; 32]));
+            exchange.elp_reserve = elp_reserve;
+            exchange.elc_reserve = elc_reserve;
+            exchange
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let exchange = Exchange::new(AccountId::from([0x1; 32]));
+            assert_eq!(exchange.reserves(), (0, 0));
+            assert_eq!(exchange.quote_elc_to_elp(100), 0);
+        }
+
+        #[ink::test]
+        fn quote_works() {
+            let exchange = new_exchange(1000, 4000);
+            assert_eq!(exchange.quote_elc_to_elp(400), 90);
+            assert_eq!(exchange.quote_elp_to_elc(100), 362);
+        }
+
+        #[ink::test]
+        fn swap_failed_without_liquidity() {
+            let mut exchange = new_exchange(0, 0);
+            assert_eq!(exchange.swap_elc_to_elp(100, 0), Err(Error::InsufficientLiquidity));
+            assert_eq!(exchange.swap_elp_to_elc(0), Err(Error::InsufficientLiquidity));
+        }
+
+        #[ink::test]
+        fn remove_liquidity_failed_without_shares() {
+            let mut exchange = new_exchange(1000, 4000);
+            assert_eq!(exchange.total_shares(), 0);
+            assert_eq!(exchange.remove_liquidity(10), Err(Error::InsufficientShares));
+        }
+    }
+}
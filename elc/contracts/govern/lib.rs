@@ -6,7 +6,7 @@ use ink_lang as ink;
 #[ink::contract]
 mod govern {
     // #[cfg(not(feature = "ink-as-dependency"))]
-    use relp::RELPRef;
+    use relp::{RELPRef, LockId, UnlockCondition};
     // #[cfg(not(feature = "ink-as-dependency"))]
     use ink_prelude::string::String;
     // #[cfg(not(feature = "ink-as-dependency"))]
@@ -31,12 +31,18 @@ mod govern {
 
     pub type Result<T> = core::result::Result<T, Error>;
 
+    /// RELP lock of the proposer, released when the proposal ends.
+    const PROPOSAL_LOCK: LockId = *b"proposal";
+    /// RELP lock of the voter, released when the voted proposal ends.
+    const VOTE_LOCK: LockId = *b"gov-vote";
+
     #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
     pub struct ProposalInfo {
         type_: u8,              // 提案类型，0代表当前无提案, 1代表治理k值, 2代表治理合约
         lock_amount: u128,      // 锁定token数量，即提案时锁定的relp数量
         begin: u32,            // 提案开始时间，以区块来计时
+        begin_time: u128,       // 提案开始时的时间戳，锁仓投票权按此时间计算
         vote_begin: u32,       // 投票开始时间，根据需求，vote_begin = 201600
         proposer: AccountId,    // 提案者
         status: u8,             // 提案状态: 1提案期间，2投票期间，3提案通过，4提案未通过
@@ -81,6 +87,8 @@ mod govern {
         relp_contract: Lazy<RELPRef>,
         // 上一次elcaim价格更新时间
         last_update_elcaim: u128,
+        // 是否使用vote-escrowed relp的衰减权重投票，否则使用relp余额
+        escrow_voting: bool,
         owner: AccountId,
     }
 
@@ -94,6 +102,7 @@ mod govern {
                 type_: 0,
                 lock_amount: 0,
                 begin: 0,
+                begin_time: 0,
                 vote_begin: 0,
                 proposer: Default::default(),
                 status: 0,
@@ -111,6 +120,7 @@ mod govern {
                 total_against_vote: Lazy::new(0),
                 relp_contract: Lazy::new(relp_contract),
                 last_update_elcaim: now_time,
+                escrow_voting: false,
                 owner,
             }
         }
@@ -118,6 +128,21 @@ mod govern {
         /// Return ELCaim price
         #[ink(message)]
         pub fn elcaim(&mut self) -> u128 {
+            let (elcaim, epochs) = self.elcaim_now();
+            // update values
+            self.elcaim = elcaim;
+            self.last_update_elcaim += (epochs * 60000 * 1000) as u128;
+            self.elcaim
+        }
+
+        /// Return ELCaim price without updating it, for read-only callers.
+        #[ink(message)]
+        pub fn current_elcaim(&self) -> u128 {
+            self.elcaim_now().0
+        }
+
+        /// Returns ELCaim at the current block and the epochs elapsed since its last update.
+        fn elcaim_now(&self) -> (u128, u128) {
             let now_time: u128 = self.env().block_timestamp().into();
             // TODO: 测试用，每隔600秒更新一次elcaim
             let epochs = (now_time - self.last_update_elcaim) / (600 * 1000);
@@ -131,10 +156,7 @@ mod govern {
                 elcaim = elcaim * (k_base + self.k) / k_base;
                 i += 1;
             }
-            // update values
-            self.elcaim = elcaim;
-            self.last_update_elcaim += (epochs * 60000 * 1000) as u128;
-            self.elcaim
+            (elcaim, epochs)
         }
 
         /// Proposal minimum RELP requirements
@@ -165,6 +187,19 @@ mod govern {
             Ok(())
         }
 
+        /// Whether votes are weighted by vote-escrowed RELP instead of RELP balance.
+        #[ink(message)]
+        pub fn escrow_voting(&self) -> bool {
+            self.escrow_voting
+        }
+
+        #[ink(message)]
+        pub fn set_escrow_voting(&mut self, enabled: bool) -> Result<()> {
+            self.only_owner()?;
+            self.escrow_voting = enabled;
+            Ok(())
+        }
+
         /// Total votes in favour of the proposal
         #[ink(message)]
         pub fn total_approve_vote(&self) -> Balance {
@@ -208,22 +243,24 @@ mod govern {
             let current_block_number = self.env().block_number();
             let delta_blocks = 1200;  // TODO: 测试用
             // let delta_blocks = 201600;  // 出块时间为6s
+            let end = current_block_number + delta_blocks * 2;
             let proposal = ProposalInfo{
                 type_: 1,
                 lock_amount,
                 begin: current_block_number,
+                begin_time: self.env().block_timestamp().into(),
                 vote_begin: current_block_number + delta_blocks,
                 proposer: caller,
                 status: 1,
-                end: current_block_number + delta_blocks * 2,
+                end,
                 new_k,
             };
 
             // 存储为新提案
             self.proposal = proposal;
 
-            // 更新lock记录
-            assert!(self.relp_contract.update_lock_infos(caller, (current_block_number, lock_amount)).is_ok());
+            // 锁定提案额度，到提案结束区块自动解锁
+            self.lock(caller, PROPOSAL_LOCK, lock_amount, end);
 
             // 触发event
             ::ink_lang::codegen::EmitEvent::<Govern>::emit_event(Self::env(), NewProposal {
@@ -237,15 +274,22 @@ mod govern {
         }
 
         /// Use to retrieve the locked balance in history.
-        /// When voting on a new proposal or start a new proposal, the locked balance will be retrieved automatically.
+        /// Locks expire when their proposal ends, this releases the ones of a replaced proposal early.
         #[ink(message)]
         pub fn withdraw_lock_amount(&mut self) {
             let user = self.env().caller();
-            let (block_number, lock_amount) = self.relp_contract.lock_info_of(user);
-            // 用户锁定balance时区块在当前提案区块前，说明是遗留的锁定额，直接将锁定额度返还给user
-            if block_number < self.proposal.begin && lock_amount != 0 {
-                // 清算后，初始化为空或者remove掉
-                assert!(self.relp_contract.update_lock_infos(user, (0, 0)).is_ok());
+            let govern = self.env().account_id();
+            let active = self.proposal.type_ != 0;
+            // 不是当前提案的提案者，提案锁定额是遗留的，直接返还给user
+            if self.relp_contract.lock_of(user, govern, PROPOSAL_LOCK).is_some()
+                && (!active || user != self.proposal.proposer) {
+                assert!(self.relp_contract.release_lock(user, PROPOSAL_LOCK).is_ok());
+            }
+            // 投票锁定额不属于当前提案
+            if let Some(lock) = self.relp_contract.lock_of(user, govern, VOTE_LOCK) {
+                if !active || lock.unlock != UnlockCondition::Block(self.proposal.end) {
+                    assert!(self.relp_contract.release_lock(user, VOTE_LOCK).is_ok());
+                }
             }
         }
 
@@ -274,7 +318,8 @@ mod govern {
             }
         }
 
-        /// Vote on the proposal by RELP, 1 RELP token for 1 vote.
+        /// Vote on the proposal by RELP, 1 RELP token for 1 vote. Votes are the RELP held or
+        /// delegated to the caller at the start block of the proposal.
         /// give `is_approve` true to approve the proposal.
         #[ink(message)]
         pub fn vote(&mut self, vote_amount: Balance, is_approve: bool) -> Result<()> {
@@ -283,26 +328,21 @@ mod govern {
                 return Err(Error::NonVotingPeriod);
             }
             let caller = self.env().caller();
-            let balance = self.relp_contract.balance_of(caller);
-            if balance < vote_amount {
+            let power = self.voting_power_of(caller);
+            if power < vote_amount {
                 return Err(Error::InsufficientBalance);
             }
 
-            let(block_number, lock_balance) = self.relp_contract.lock_info_of(caller);
-            if block_number > self.proposal.vote_begin {
-                return Err(Error::AlreadyVoted);
-            }
-
-            // 提案者自己投票 
-            let cur_block_num = self.env().block_number();
-            if caller == self.proposal.proposer {
-                assert!(self.relp_contract.update_lock_infos(caller, (cur_block_num, lock_balance + vote_amount)).is_ok());
-                self.update_votes(vote_amount, is_approve);
-                return Ok(()) 
+            let govern = self.env().account_id();
+            if let Some(lock) = self.relp_contract.lock_of(caller, govern, VOTE_LOCK) {
+                if lock.unlock == UnlockCondition::Block(self.proposal.end) {
+                    return Err(Error::AlreadyVoted);
+                }
             }
 
-            // 更新锁定记录
-            assert!(self.relp_contract.update_lock_infos(caller, (cur_block_num, vote_amount)).is_ok());
+            // 票数取自提案开始区块的快照(或escrow中的relp)，之后转账不影响票数，
+            // 投票锁不再锁定余额，只用来标记已投票
+            self.lock(caller, VOTE_LOCK, 0, self.proposal.end);
             self.update_votes(vote_amount, is_approve);
             Ok(())
         }
@@ -331,6 +371,41 @@ mod govern {
             Ok(())
         }
 
+        /// Block the votes are read at, the one before the proposal so that balances
+        /// moved in the proposing block do not count.
+        fn snapshot_block(&self) -> u32 {
+            self.proposal.begin.saturating_sub(1)
+        }
+
+        /// Votes of `user` on the current proposal, including the RELP delegated to it
+        /// before the start block of the proposal. Escrow weights are taken at its start time.
+        fn voting_power_of(&self, user: AccountId) -> Balance {
+            if self.escrow_voting {
+                self.relp_contract.voting_power_at(user, self.snapshot_block(), self.proposal.begin_time)
+            } else {
+                self.relp_contract.votes_at(user, self.snapshot_block())
+            }
+        }
+
+        fn total_voting_power(&self) -> Balance {
+            if self.escrow_voting {
+                self.relp_contract
+                    .total_voting_power_at(self.snapshot_block(), self.proposal.begin_time)
+                    .expect("Total voting power underflow")
+            } else {
+                self.relp_contract.total_supply_at(self.snapshot_block())
+            }
+        }
+
+        /// Replace the lock `id` of `user` with a new one until block `end`.
+        fn lock(&mut self, user: AccountId, id: LockId, amount: Balance, end: u32) {
+            let govern = self.env().account_id();
+            if self.relp_contract.lock_of(user, govern, id).is_some() {
+                assert!(self.relp_contract.release_lock(user, id).is_ok());
+            }
+            assert!(self.relp_contract.set_lock(user, id, amount, UnlockCondition::Block(end)).is_ok());
+        }
+
         fn counting_vote(&mut self) -> u8 {
             // 投票人数未达标，直接否决
             if self.total_account < self.accounts_needs as u128 {
@@ -341,10 +416,10 @@ mod govern {
 
             let approve = self.total_approve_vote();
             let against = self.total_against_vote();
-            let total_relp_supply = self.relp_contract.total_supply();            
-            assert!(approve + against > 0 && total_relp_supply > 0, "Amount of votes and relp total supply must > 0");
+            let total_power = self.total_voting_power();
+            assert!(approve + against > 0 && total_power > 0, "Amount of votes and total voting power must > 0");
             let a = against * against / (approve + against);
-            let b = approve * approve / total_relp_supply;
+            let b = approve * approve / total_power;
             if a < b { 
                 // 提案通过
                 self.k = self.proposal.new_k;
@@ -438,6 +513,17 @@ mod govern {
             assert_eq!(govern.set_accounts_needs(200), Err(Error::OnlyOwnerAccess));
         }
 
+        #[ink::test]
+        fn set_escrow_voting_works() {
+            let mut govern = Govern::new(AccountId::from([0x01; 32]));
+            let accounts = default_accounts();
+            assert!(!govern.escrow_voting());
+            assert!(govern.set_escrow_voting(true).is_ok());
+            assert!(govern.escrow_voting());
+            assert!(govern.transfer_ownership(accounts.bob).is_ok());
+            assert_eq!(govern.set_escrow_voting(false), Err(Error::OnlyOwnerAccess));
+        }
+
         #[ink::test]
         fn clean_vote_info_works() {
             let mut govern = Govern::new(AccountId::from([0x01; 32]));
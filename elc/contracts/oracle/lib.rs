@@ -4,6 +4,8 @@ use ink_lang as ink;
 
 #[ink::contract]
 mod oracle {
+    use ink_storage::collections::HashMap as StorageHashMap;
+
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
@@ -22,6 +24,8 @@ mod oracle {
         elc_price: u128,
         block_timestamp_last: u128,
         owner: AccountId,
+        /// Prices of collateral assets, same decimals as ELP.
+        asset_prices: StorageHashMap<AccountId, u128>,
     }
 
     impl Oracle {
@@ -34,6 +38,7 @@ mod oracle {
                 elc_price: 0,
                 block_timestamp_last: 0,
                 owner: caller,
+                asset_prices: StorageHashMap::new(),
             }
         }
 
@@ -53,6 +58,21 @@ mod oracle {
         #[ink(message)]
         pub fn elc_price(&self) -> u128 { self.elc_price }
 
+        /// update price of a collateral asset, decimal is 100000
+        #[ink(message)]
+        pub fn update_asset(&mut self, asset: AccountId, price: u128) -> Result<()> {
+            self.only_owner()?;
+            self.asset_prices.insert(asset, price);
+            self.block_timestamp_last = Self::env().block_timestamp().into();
+            Ok(())
+        }
+
+        /// price of a collateral asset, 0 if not fed.
+        #[ink(message)]
+        pub fn asset_price(&self, asset: AccountId) -> u128 {
+            self.asset_prices.get(&asset).copied().unwrap_or(0)
+        }
+
         fn only_owner(&self) -> Result<()> {
             let caller = self.env().caller();
             if caller != self.owner {
@@ -111,6 +131,15 @@ mod oracle {
             assert_eq!(oracle.elc_price(), 99);
         }
 
+        #[ink::test]
+        fn update_asset_works() {
+            let mut oracle = Oracle::new();
+            let asset = AccountId::from([0x1; 32]);
+            assert_eq!(oracle.asset_price(asset), 0);
+            assert!(oracle.update_asset(asset, 120).is_ok());
+            assert_eq!(oracle.asset_price(asset), 120);
+        }
+
         #[ink::test]
         fn update_failed_when_not_owner() {
             let mut oracle = Oracle::new();
@@ -1,6 +1,6 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-pub use self::relp::{RELP, RELPRef};
+pub use self::relp::{RELP, RELPRef, Escrow, EscrowPoint, Lock, LockId, UnlockCondition, VoteCheckpoint};
 use ink_lang as ink;
 
 #[ink::contract]
@@ -8,11 +8,13 @@ mod relp {
     use elc::ELCRef;
     use reward::RewardRef;
     use additional::AdditionalRef;
-    use ink_prelude::{string::String};
+    use coinday::{add, mul, mul_div, sub, CoindayLedger, Distribution, Elc, Elp, MathError, RewardAsset};
+    use ink_prelude::{string::String, vec::Vec};
     // #[cfg(not(feature = "ink-as-dependency"))]
     use ink_storage::{
         collections::HashMap as StorageHashMap,
         lazy::Lazy,
+        traits::{PackedLayout, SpreadLayout},
     };
     // #[cfg(not(feature = "ink-as-dependency"))]
     use ink_env::call::FromAccountId;
@@ -29,8 +31,106 @@ mod relp {
         IntervalTooShort,
         NeedLiquidateBlockReward,
         NeedLiquidateIncreaseReward,
+        BatchTooLarge,
+        InvalidBountyRate,
+        InvalidSettlementLimit,
+        InvalidTransferFee,
+        OnlyLockerAccess,
+        LockAlreadyExists,
+        LockNotFound,
+        InvalidLockExtension,
+        TooManyLocks,
+        EscrowAlreadyExists,
+        EscrowNotFound,
+        EscrowExpired,
+        EscrowNotExpired,
+        InvalidUnlockTime,
+        /// No ELP vesting position in reward contract.
+        VestingNotFound,
+        Overflow,
+        Underflow,
     }
 
+    impl From<MathError> for Error {
+        fn from(error: MathError) -> Self {
+            match error {
+                MathError::Overflow => Error::Overflow,
+                MathError::Underflow => Error::Underflow,
+            }
+        }
+    }
+
+    /// Identifier of a lock, unique per locker contract.
+    pub type LockId = [u8; 8];
+
+    /// Max active locks of one account.
+    const MAX_LOCKS: usize = 16;
+
+    /// Condition under which a lock stops restricting the balance.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub enum UnlockCondition {
+        /// Unlocked from this block number on.
+        Block(u32),
+        /// Unlocked from this timestamp on, in milliseconds.
+        Timestamp(u128),
+        /// Locked until the locker releases it.
+        Manual,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Lock {
+        pub locker: AccountId,      // 创建该锁的合约，只有它可以延长或释放
+        pub id: LockId,
+        pub amount: Balance,
+        pub unlock: UnlockCondition,
+    }
+
+    /// Vote-escrowed RELP of an account, locked until `end`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Escrow {
+        pub amount: Balance,
+        pub end: u128,      // 解锁时间，按周取整
+    }
+
+    /// Delegated votes of an account from block `from_block` on.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct VoteCheckpoint {
+        pub from_block: u32,
+        pub votes: Balance,
+    }
+
+    /// Escrow sums of an account, or of all accounts, from block `from_block` on. The voting
+    /// weight at time `t` is `(bias - slope * t) / MAX_ESCROW_TIME`, for all accounts after
+    /// the escrows ended between `timestamp` and `t` are taken out.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct EscrowPoint {
+        pub from_block: u32,
+        pub slope: Balance,
+        pub bias: u128,
+        pub timestamp: u128,
+    }
+
+    /// Unlock times of escrows are rounded down to whole weeks, in milliseconds.
+    const ESCROW_EPOCH: u128 = 7 * 24 * 3600 * 1000;
+    /// Max escrow duration, 4 years.
+    const MAX_ESCROW_TIME: u128 = 4 * 365 * 24 * 3600 * 1000;
+    /// Extra coinday balance of escrowed RELP locked for the max duration, base 10000.
+    const MAX_ESCROW_BOOST: u128 = 15000;
+
+    /// Max users settled by one batch liquidation.
+    const MAX_LIQUIDATION_BATCH: usize = 20;
+    /// Max keeper bounty of batch liquidation, base 10000.
+    const MAX_LIQUIDATION_BOUNTY: u128 = 100;
+    /// Upper bound of both settlement limits, keeps one settlement within block weight.
+    const MAX_SETTLEMENT_PERIODS: u32 = 100;
+    /// Max transfer fee, base 10000.
+    const MAX_TRANSFER_FEE: u128 = 100;
+
     /// The RELP result type.
     pub type Result<T> = core::result::Result<T, Error>;
 
@@ -46,8 +146,10 @@ mod relp {
         total_supply: Lazy<Balance>,
         /// Mapping from owner to number of owned token.
         balances: StorageHashMap<AccountId, Balance>,
-        /// Mapping from owner to a tuple(block_number, lock_balance).
-        lock_infos: StorageHashMap<AccountId, (u32, Balance)>,
+        /// Mapping from owner to its locks, locks overlap each other.
+        locks: StorageHashMap<AccountId, Vec<Lock>>,
+        /// Contracts allowed to lock balances, e.g. govern.
+        lockers: StorageHashMap<AccountId, bool>,
         /// Mapping of the token amount which an account is allowed to withdraw
         /// from another account.
         allowances: StorageHashMap<(AccountId, AccountId), Balance>,
@@ -57,6 +159,48 @@ mod relp {
         reward_contract: Lazy<RewardRef>,
         /// additional contract
         add_contract: Lazy<AdditionalRef>,
+        /// additional contract address, vested ELC is minted to it
+        add_accountid: AccountId,
+        /// Share of settled rewards paid to the keeper of batch liquidation, base 10000.
+        liquidation_bounty: u128,
+        /// Max uncollected periods settled implicitly by transfer, mint and burn, more than
+        /// it must be liquidated first.
+        max_collect_periods: u32,
+        /// Max periods settled for one user by a liquidation call.
+        max_liquidate_periods: u32,
+        /// Fee of transfers, base 10000, paid in RELP to `fee_recipient`.
+        transfer_fee: u128,
+        /// Receiver of transfer fees, `Stable` turns them into its risk reserve.
+        fee_recipient: Option<AccountId>,
+        /// Accounts whose transfers are free of fee, e.g. protocol contracts.
+        fee_exempt: StorageHashMap<AccountId, bool>,
+        /// Vote-escrowed RELP of accounts, still held in their balances.
+        escrows: StorageHashMap<AccountId, Escrow>,
+        /// Sum of amounts of escrows not ended at `escrow_checkpoint`.
+        escrow_slope: Balance,
+        /// Sum of amount * end of escrows not ended at `escrow_checkpoint`.
+        escrow_bias: u128,
+        /// Last time the escrow sums were brought up to date.
+        escrow_checkpoint: u128,
+        /// Mapping from epoch to the escrowed amount ending at it, past epochs are kept for
+        /// the historical totals.
+        slope_changes: StorageHashMap<u128, Balance>,
+        /// Mapping from (account, index) to the history of its escrow,
+        /// `None` keeps the history of the escrow sums.
+        escrow_points: StorageHashMap<(Option<AccountId>, u32), EscrowPoint>,
+        /// Number of escrow points of each account.
+        escrow_points_len: StorageHashMap<Option<AccountId>, u32>,
+        /// Mapping from owner to the extra coinday balance earned by its escrow.
+        boosts: StorageHashMap<AccountId, Balance>,
+        /// Sum of all boosts.
+        total_boost: Balance,
+        /// Mapping from delegator to its delegate, accounts not in it vote for themselves.
+        delegates: StorageHashMap<AccountId, AccountId>,
+        /// Mapping from (account, index) to the checkpoints of its votes,
+        /// `None` keeps the checkpoints of the total supply.
+        checkpoints: StorageHashMap<(Option<AccountId>, u32), VoteCheckpoint>,
+        /// Number of checkpoints of each account.
+        checkpoints_len: StorageHashMap<Option<AccountId>, u32>,
         /// The contract owner, provides basic authorization control
         /// functions, this simplifies the implementation of "user permissions".
         owner: AccountId,
@@ -101,6 +245,96 @@ mod relp {
         amount: Balance,
     }
 
+    /// Event emitted when a keeper settles increase reward of many users.
+    #[ink(event)]
+    pub struct IncreaseRewardsLiquidated {
+        #[ink(topic)]
+        keeper: AccountId,
+        settled: u32,
+        bounty: Balance,
+    }
+
+    /// Event emitted when a keeper settles block reward of many users.
+    #[ink(event)]
+    pub struct BlockRewardsLiquidated {
+        #[ink(topic)]
+        keeper: AccountId,
+        settled: u32,
+        bounty: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TransferFeeUpdated {
+        rate: u128,
+        recipient: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct SettlementLimitsUpdated {
+        max_collect_periods: u32,
+        max_liquidate_periods: u32,
+    }
+
+    #[ink(event)]
+    pub struct LockUpdated {
+        #[ink(topic)]
+        user: AccountId,
+        #[ink(topic)]
+        locker: AccountId,
+        id: LockId,
+        amount: Balance,
+        unlock: UnlockCondition,
+    }
+
+    #[ink(event)]
+    pub struct LockReleased {
+        #[ink(topic)]
+        user: AccountId,
+        #[ink(topic)]
+        locker: AccountId,
+        id: LockId,
+    }
+
+    #[ink(event)]
+    pub struct EscrowUpdated {
+        #[ink(topic)]
+        user: AccountId,
+        amount: Balance,
+        end: u128,
+    }
+
+    #[ink(event)]
+    pub struct EscrowWithdrawn {
+        #[ink(topic)]
+        user: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BoostUpdated {
+        #[ink(topic)]
+        user: AccountId,
+        boost: Balance,
+    }
+
+    #[ink(event)]
+    pub struct DelegateChanged {
+        #[ink(topic)]
+        delegator: AccountId,
+        #[ink(topic)]
+        from_delegate: AccountId,
+        #[ink(topic)]
+        to_delegate: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct DelegateVotesChanged {
+        #[ink(topic)]
+        delegate: AccountId,
+        previous_votes: Balance,
+        new_votes: Balance,
+    }
+
     impl RELP {
         #[ink(constructor)]
         pub fn new(
@@ -109,6 +343,7 @@ mod relp {
             additional_addr: AccountId, 
         ) -> Self {
             let caller = Self::env().caller();
+            let now_time = Self::env().block_timestamp().into();
             let name: Option<String> = Some(String::from("Risk Reserve of ELP"));
             let symbol: Option<String> = Some(String::from("rELP"));
             let decimals: Option<u8> = Some(8);
@@ -121,11 +356,31 @@ mod relp {
                 decimals,
                 total_supply: Lazy::new(0),
                 balances: StorageHashMap::new(),
-                lock_infos: StorageHashMap::new(),
+                locks: StorageHashMap::new(),
+                lockers: StorageHashMap::new(),
                 allowances: StorageHashMap::new(),
                 elc_contract: Lazy::new(elc_contract),
                 reward_contract: Lazy::new(reward_contract),
                 add_contract: Lazy::new(add_contract),
+                add_accountid: additional_addr,
+                liquidation_bounty: 10,
+                max_collect_periods: 50,
+                max_liquidate_periods: 50,
+                transfer_fee: 0,
+                fee_recipient: None,
+                fee_exempt: StorageHashMap::new(),
+                escrows: StorageHashMap::new(),
+                escrow_slope: 0,
+                escrow_bias: 0,
+                escrow_checkpoint: now_time,
+                slope_changes: StorageHashMap::new(),
+                escrow_points: StorageHashMap::new(),
+                escrow_points_len: StorageHashMap::new(),
+                boosts: StorageHashMap::new(),
+                total_boost: 0,
+                delegates: StorageHashMap::new(),
+                checkpoints: StorageHashMap::new(),
+                checkpoints_len: StorageHashMap::new(),
                 owner: caller,
             }
         }
@@ -162,15 +417,377 @@ mod relp {
             self.balances.get(&owner).copied().unwrap_or(0)
         }
 
+        /// All locks of `user`, expired ones included until they are released.
+        #[ink(message)]
+        pub fn locks_of(&self, user: AccountId) -> Vec<Lock> {
+            self.locks.get(&user).cloned().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn lock_of(&self, user: AccountId, locker: AccountId, id: LockId) -> Option<Lock> {
+            self.locks_of(user).into_iter().find(|lock| lock.locker == locker && lock.id == id)
+        }
+
+        /// Locked balance of `user`, the max amount of its active locks and escrow.
+        #[ink(message)]
+        pub fn locked_balance_of(&self, user: AccountId) -> Balance {
+            let now_time: u128 = self.env().block_timestamp().into();
+            let escrowed = match self.escrows.get(&user) {
+                Some(escrow) if escrow.end > now_time => escrow.amount,
+                _ => 0,
+            };
+            self.locks_of(user)
+                .iter()
+                .filter(|lock| self.is_active(lock))
+                .map(|lock| lock.amount)
+                .fold(escrowed, core::cmp::max)
+        }
+
+        /// Balance of `user` which can be transferred or burned.
+        #[ink(message)]
+        pub fn free_balance_of(&self, user: AccountId) -> Balance {
+            self.balance_of(user).saturating_sub(self.locked_balance_of(user))
+        }
+
+        /// Add a lock on `user` identified by the caller and `id`.
+        #[ink(message)]
+        pub fn set_lock(&mut self, user: AccountId, id: LockId, amount: Balance, unlock: UnlockCondition) -> Result<()> {
+            let locker = self.only_locker()?;
+            let mut locks = self.locks_of(user);
+            // 过期的锁不再生效，直接清理
+            locks.retain(|lock| self.is_active(lock));
+            if locks.iter().any(|lock| lock.locker == locker && lock.id == id) {
+                return Err(Error::LockAlreadyExists)
+            }
+            if locks.len() >= MAX_LOCKS {
+                return Err(Error::TooManyLocks)
+            }
+            locks.push(Lock { locker, id, amount, unlock });
+            self.locks.insert(user, locks);
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), LockUpdated { user, locker, id, amount, unlock });
+            Ok(())
+        }
+
+        /// Raise the amount and/or postpone the unlock condition of a lock of the caller.
+        /// The lock can never be loosened, the unlock condition must keep its kind.
+        #[ink(message)]
+        pub fn extend_lock(&mut self, user: AccountId, id: LockId, amount: Balance, unlock: UnlockCondition) -> Result<()> {
+            let locker = self.only_locker()?;
+            let mut locks = self.locks_of(user);
+            let lock = locks.iter_mut()
+                .find(|lock| lock.locker == locker && lock.id == id)
+                .ok_or(Error::LockNotFound)?;
+            let later = match (lock.unlock, unlock) {
+                (UnlockCondition::Block(old), UnlockCondition::Block(new)) => new >= old,
+                (UnlockCondition::Timestamp(old), UnlockCondition::Timestamp(new)) => new >= old,
+                (UnlockCondition::Manual, UnlockCondition::Manual) => true,
+                _ => false,
+            };
+            if amount < lock.amount || !later {
+                return Err(Error::InvalidLockExtension)
+            }
+            lock.amount = amount;
+            lock.unlock = unlock;
+            self.locks.insert(user, locks);
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), LockUpdated { user, locker, id, amount, unlock });
+            Ok(())
+        }
+
+        /// Remove a lock of the caller on `user`.
+        #[ink(message)]
+        pub fn release_lock(&mut self, user: AccountId, id: LockId) -> Result<()> {
+            let locker = self.only_locker()?;
+            let mut locks = self.locks_of(user);
+            let len = locks.len();
+            locks.retain(|lock| lock.locker != locker || lock.id != id);
+            if locks.len() == len {
+                return Err(Error::LockNotFound)
+            }
+            if locks.is_empty() {
+                self.locks.take(&user);
+            } else {
+                self.locks.insert(user, locks);
+            }
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), LockReleased { user, locker, id });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn escrow_of(&self, user: AccountId) -> Option<Escrow> {
+            self.escrows.get(&user).cloned()
+        }
+
+        /// Voting weight of `user`, decays linearly to zero at the unlock time of its escrow.
+        #[ink(message)]
+        pub fn voting_power_of(&self, user: AccountId) -> Balance {
+            let now_time = self.env().block_timestamp().into();
+            self.escrows.get(&user).map_or(0, |escrow| Self::escrow_power(escrow, now_time))
+        }
+
+        /// Sum of the voting weights of all accounts.
+        #[ink(message)]
+        pub fn total_voting_power(&self) -> Result<Balance> {
+            let now_time = self.env().block_timestamp().into();
+            let (slope, bias) = self.escrow_totals(now_time)?;
+            point_power(slope, bias, now_time)
+        }
+
+        /// Voting weight of `user` at `time`, with its escrow as of the end of block `block_number`.
+        #[ink(message)]
+        pub fn voting_power_at(&self, user: AccountId, block_number: u32, time: u128) -> Balance {
+            // 个人检查点的 bias = amount * end，到期后权重为0
+            self.escrow_point_at(Some(user), block_number)
+                .filter(|point| point.slope > 0)
+                .map_or(0, |point| {
+                    Self::escrow_power(&Escrow { amount: point.slope, end: point.bias / point.slope }, time)
+                })
+        }
+
+        /// Sum of the voting weights at `time`, with the escrows as of the end of block `block_number`.
+        #[ink(message)]
+        pub fn total_voting_power_at(&self, block_number: u32, time: u128) -> Result<Balance> {
+            match self.escrow_point_at(None, block_number) {
+                Some(point) => {
+                    let (slope, bias) = self.totals_from(point.slope, point.bias, point.timestamp, time)?;
+                    point_power(slope, bias, time)
+                }
+                None => Ok(0),
+            }
+        }
+
+        /// Extra coinday balance of `user` earned by its escrow.
+        #[ink(message)]
+        pub fn boost_of(&self, user: AccountId) -> Balance {
+            self.boosts.get(&user).copied().unwrap_or(0)
+        }
+
+        /// Balance of `user` accruing coinday in the reward streams, boost included.
+        #[ink(message)]
+        pub fn coinday_balance_of(&self, user: AccountId) -> Result<Balance> {
+            Ok(add(self.balance_of(user), self.boost_of(user))?)
+        }
+
+        /// Total balance accruing coinday in the reward streams, boosts included.
+        #[ink(message)]
+        pub fn coinday_supply(&self) -> Result<Balance> {
+            Ok(add(*self.total_supply, self.total_boost)?)
+        }
+
+        /// Escrow `amount` RELP of the caller until `end`, rounded down to whole weeks.
+        #[ink(message)]
+        pub fn create_escrow(&mut self, amount: Balance, end: u128) -> Result<()> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            if self.escrows.get(&caller).is_some() {
+                return Err(Error::EscrowAlreadyExists)
+            }
+            if amount == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            if self.balance_of(caller) < amount {
+                return Err(Error::InsufficientFreeBalance)
+            }
+            let end = Self::unlock_time(end, now_time)?;
+            self.deposit_escrow(caller, Escrow { amount, end }, now_time)?;
+            self.sync_boost(caller)
+        }
+
+        /// Add `amount` RELP of the caller to its escrow, keeping the unlock time.
+        #[ink(message)]
+        pub fn increase_escrow_amount(&mut self, amount: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            let escrow = self.active_escrow(caller, now_time)?;
+            if amount == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            if self.balance_of(caller) < escrow.amount + amount {
+                return Err(Error::InsufficientFreeBalance)
+            }
+            self.deposit_escrow(caller, Escrow { amount: escrow.amount + amount, end: escrow.end }, now_time)?;
+            self.sync_boost(caller)
+        }
+
+        /// Postpone the unlock time of the caller's escrow to `end`, rounded down to whole weeks.
+        #[ink(message)]
+        pub fn extend_escrow(&mut self, end: u128) -> Result<()> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            let escrow = self.active_escrow(caller, now_time)?;
+            let end = Self::unlock_time(end, now_time)?;
+            if end <= escrow.end {
+                return Err(Error::InvalidUnlockTime)
+            }
+            self.deposit_escrow(caller, Escrow { amount: escrow.amount, end }, now_time)?;
+            self.sync_boost(caller)
+        }
+
+        /// Close the caller's escrow after its unlock time.
+        #[ink(message)]
+        pub fn withdraw_escrow(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            let now_time: u128 = self.env().block_timestamp().into();
+            let escrow = self.escrows.get(&caller).cloned().ok_or(Error::EscrowNotFound)?;
+            if now_time < escrow.end {
+                return Err(Error::EscrowNotExpired)
+            }
+            self.escrows.take(&caller);
+            self.write_escrow_point(Some(caller), 0, 0, now_time);
+            self.sync_boost(caller)?;
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), EscrowWithdrawn {
+                user: caller,
+                amount: escrow.amount,
+            });
+            Ok(())
+        }
+
+        /// Recalculate the boost of `user` from its escrow. The boost decays with the escrow, it
+        /// is updated whenever the coinday of `user` is, anyone may call this in between.
+        #[ink(message)]
+        pub fn update_boost(&mut self, user: AccountId) -> Result<()> {
+            self.sync_boost(user)
+        }
+
+        /// Close the caller's ELP vesting position in reward contract before its end. The
+        /// coinday of the caller and the total coinday are brought up to now first, the exit
+        /// penalty goes to the other holders by their coinday at this moment.
+        #[ink(message)]
+        pub fn exit_elp_vesting(&mut self) -> Result<Balance> {
+            let user = self.env().caller();
+            if self.reward_contract.vesting_of(user).is_none() {
+                return Err(Error::VestingNotFound)
+            }
+            self.sync_boost(user)?;
+            let coinday_balance = self.coinday_balance_of(user)?;
+            let (timestamp, index_elp) = self.get_elp_reward(user)?;
+            Distribution::<Elp>::increase(&mut *self.reward_contract, user, coinday_balance, timestamp, index_elp)?;
+            let coinday_supply = self.coinday_supply()?;
+            let total_coinday = Distribution::<Elp>::update_total(&mut *self.reward_contract, coinday_supply, timestamp, 0)?;
+            // 罚金按除退出者外的当前总币天分配
+            let others_coinday = sub(total_coinday, self.reward_contract.get_coinday_info(user).amount)?;
+            let amount = self.reward_contract.exit_vesting(user, others_coinday).expect("Exit vesting failed");
+            Ok(amount)
+        }
+
+        /// The account `user` delegates its votes to, itself by default.
+        #[ink(message)]
+        pub fn delegates_of(&self, user: AccountId) -> AccountId {
+            self.delegates.get(&user).copied().unwrap_or(user)
+        }
+
+        /// Delegate the votes of the caller's balance to `to`, tokens stay with the caller.
+        /// Delegate to oneself to take the votes back.
+        #[ink(message)]
+        pub fn delegate(&mut self, to: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            let from = self.delegates_of(caller);
+            if from == to {
+                return Ok(())
+            }
+            if to == caller {
+                self.delegates.take(&caller);
+            } else {
+                self.delegates.insert(caller, to);
+            }
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), DelegateChanged {
+                delegator: caller,
+                from_delegate: from,
+                to_delegate: to,
+            });
+            let balance = self.balance_of(caller);
+            self.move_votes(Some(from), Some(to), balance);
+            Ok(())
+        }
+
+        /// Current votes of `user`, its own balance if not delegated plus the balances delegated to it.
+        #[ink(message)]
+        pub fn votes_of(&self, user: AccountId) -> Balance {
+            self.checkpoint_at(Some(user), u32::MAX)
+        }
+
+        /// Votes of `user` at the end of block `block_number`.
+        #[ink(message)]
+        pub fn votes_at(&self, user: AccountId, block_number: u32) -> Balance {
+            self.checkpoint_at(Some(user), block_number)
+        }
+
+        /// Total supply at the end of block `block_number`.
+        #[ink(message)]
+        pub fn total_supply_at(&self, block_number: u32) -> Balance {
+            self.checkpoint_at(None, block_number)
+        }
+
+        #[ink(message)]
+        pub fn checkpoints_of(&self, user: AccountId, index: u32) -> Option<VoteCheckpoint> {
+            self.checkpoints.get(&(Some(user), index)).copied()
+        }
+
+        /// Returns `(rate, recipient)` of transfer fee, rate base 10000.
+        #[ink(message)]
+        pub fn transfer_fee(&self) -> (u128, Option<AccountId>) {
+            (self.transfer_fee, self.fee_recipient)
+        }
+
+        /// Set the transfer fee, it is taken only when `recipient` is set.
+        #[ink(message)]
+        pub fn set_transfer_fee(&mut self, rate: u128, recipient: Option<AccountId>) -> Result<()> {
+            self.only_owner()?;
+            if rate > MAX_TRANSFER_FEE {
+                return Err(Error::InvalidTransferFee)
+            }
+            self.transfer_fee = rate;
+            self.fee_recipient = recipient;
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), TransferFeeUpdated {
+                rate,
+                recipient,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_fee_exempt(&self, user: AccountId) -> bool {
+            self.fee_exempt.get(&user).copied().unwrap_or(false)
+        }
+
+        #[ink(message)]
+        pub fn set_fee_exempt(&mut self, user: AccountId, exempt: bool) -> Result<()> {
+            self.only_owner()?;
+            if exempt {
+                self.fee_exempt.insert(user, true);
+            } else {
+                self.fee_exempt.take(&user);
+            }
+            Ok(())
+        }
+
+        /// Fee taken from a transfer of `value` from `from` to `to`, the receiver gets the rest.
+        #[ink(message)]
+        pub fn transfer_fee_of(&self, from: AccountId, to: AccountId, value: Balance) -> Result<Balance> {
+            match self.fee_recipient {
+                Some(recipient) if recipient != from && recipient != to
+                    && !self.is_fee_exempt(from) && !self.is_fee_exempt(to) => {
+                    fee_for(value, self.transfer_fee)
+                }
+                _ => Ok(0),
+            }
+        }
+
+        #[ink(message)]
+        pub fn is_locker(&self, locker: AccountId) -> bool {
+            self.lockers.get(&locker).copied().unwrap_or(false)
+        }
+
         #[ink(message)]
-        pub fn lock_info_of(&self, user: AccountId) -> (u32, Balance) {
-            self.lock_infos.get(&user).copied().unwrap_or((0, 0))
+        pub fn grant_locker(&mut self, locker: AccountId) -> Result<()> {
+            self.only_owner()?;
+            self.lockers.insert(locker, true);
+            Ok(())
         }
 
         #[ink(message)]
-        pub fn update_lock_infos(&mut self, user: AccountId, lock_info: (u32, Balance)) -> Result<()> {
+        pub fn revoke_locker(&mut self, locker: AccountId) -> Result<()> {
             self.only_owner()?;
-            self.lock_infos.insert(user, lock_info);
+            self.lockers.take(&locker);
             Ok(())
         }
 
@@ -250,20 +867,22 @@ mod relp {
                 return Err(Error::InvalidAmount);
             }
 
+            self.sync_boost(user)?;
             let user_balance = self.balance_of(user);
+            let coinday_balance = self.coinday_balance_of(user)?;
             // calculate ELC reward
             let (timestamp, index) = self.get_elc_reward(user)?;
-            self.increase_coinday_elc(user, timestamp, index);
+            Distribution::<Elc>::increase(&mut *self.add_contract, user, coinday_balance, timestamp, index)?;
 
             // calculate ELP reward
             let (_, index_elp) = self.get_elp_reward(user)?;
-            self.increase_coinday_elp(user, timestamp, index_elp);
+            Distribution::<Elp>::increase(&mut *self.reward_contract, user, coinday_balance, timestamp, index_elp)?;
             self.balances.insert(user, user_balance + amount); 
             
             // update total coinday
-            self.update_total_elc(timestamp, 0);
-            self.update_total_elp(timestamp, 0);
-            *self.total_supply += amount;
+            self.update_total_coinday(timestamp, 0, 0)?;
+            *self.total_supply = add(*self.total_supply, amount)?;
+            self.move_votes(None, Some(self.delegates_of(user)), amount);
             ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), Mint {
             // self.env().emit_event(Mint { 
                 user,
@@ -284,24 +903,27 @@ mod relp {
             }
 
             let user_balance = self.balance_of(user);
-            let (_, lock_balance) = self.lock_info_of(user);
-            if user_balance - lock_balance < amount {
+            if self.free_balance_of(user) < amount {
                 return Err(Error::InsufficientFreeBalance);
             }
+            self.sync_boost(user)?;
+            let coinday_balance = self.coinday_balance_of(user)?;
 
             // calculate ELC reward
             let (timestamp, index) = self.get_elc_reward(user)?;
-            let decrease = self.decrease_coinday_elc(user, amount, timestamp, index);
+            let decrease = Distribution::<Elc>::decrease(
+                &mut *self.add_contract, user, coinday_balance, amount, timestamp, index)?;
 
             // calculate ELP reward
             let (_, index_elp) = self.get_elp_reward(user)?;
-            let decrease_elp = self.decrease_coinday_elp(user, amount, timestamp, index_elp);
+            let decrease_elp = Distribution::<Elp>::decrease(
+                &mut *self.reward_contract, user, coinday_balance, amount, timestamp, index_elp)?;
             self.balances.insert(user, user_balance - amount); 
             
             // update total coinday
-            self.update_total_elc(timestamp, decrease);
-            self.update_total_elp(timestamp, decrease_elp);
-            *self.total_supply -= amount;
+            self.update_total_coinday(timestamp, decrease, decrease_elp)?;
+            *self.total_supply = sub(*self.total_supply, amount)?;
+            self.move_votes(Some(self.delegates_of(user)), None, amount);
             ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), Burn {
             // self.env().emit_event(Burn { 
                 user, 
@@ -324,34 +946,55 @@ mod relp {
             to: AccountId,
             value: Balance,
         ) -> Result<()> {
-            let from_balance = self.balance_of(from);
-            let (_, lock_balance) = self.lock_info_of(from);
-            if from_balance - lock_balance < value {
+            if self.free_balance_of(from) < value {
                 return Err(Error::InsufficientFreeBalance);
             }
+            let fee = self.transfer_fee_of(from, to, value)?;
+            self.move_balance(from, to, value - fee)?;
+            // 手续费按普通转账处理，币天随之转给手续费接收者
+            if let (Some(recipient), true) = (self.fee_recipient, fee > 0) {
+                self.move_balance(from, recipient, fee)?;
+            }
+            Ok(())
+        }
+
+        fn move_balance(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            // 先按锁仓衰减更新boost，再按新的币天余额结算
+            self.sync_boost(from)?;
+            self.sync_boost(to)?;
+            let from_balance = self.balance_of(from);
+            let from_coinday_balance = self.coinday_balance_of(from)?;
             // Calculate current ELC rewards
             let (timestamp, index_fr) = self.get_elc_reward(from)?;
-            let decrease = self.decrease_coinday_elc(from, value, timestamp, index_fr);
+            let decrease = Distribution::<Elc>::decrease(
+                &mut *self.add_contract, from, from_coinday_balance, value, timestamp, index_fr)?;
 
             // Calculate current ELP rewards
             let (_, index_fr_elp) = self.get_elp_reward(from)?;
-            let decrease_elp = self.decrease_coinday_elp(from, value, timestamp, index_fr_elp);
+            let decrease_elp = Distribution::<Elp>::decrease(
+                &mut *self.reward_contract, from, from_coinday_balance, value, timestamp, index_fr_elp)?;
             self.balances.insert(from, from_balance - value);
 
 
             let to_balance = self.balance_of(to);
+            let to_coinday_balance = self.coinday_balance_of(to)?;
             // Calculate current ELC rewards
             let (_, index_to) = self.get_elc_reward(to)?;
-            self.increase_coinday_elc(to, timestamp, index_to);
+            Distribution::<Elc>::increase(&mut *self.add_contract, to, to_coinday_balance, timestamp, index_to)?;
 
             // Calculate current ELP rewards
             let (_, index_to_elp) = self.get_elp_reward(to)?;
-            self.increase_coinday_elp(to, timestamp, index_to_elp);
+            Distribution::<Elp>::increase(&mut *self.reward_contract, to, to_coinday_balance, timestamp, index_to_elp)?;
             self.balances.insert(to, to_balance + value);
             
             // update total coinday
-            self.update_total_elc(timestamp, decrease);
-            self.update_total_elp(timestamp, decrease_elp);
+            self.update_total_coinday(timestamp, decrease, decrease_elp)?;
+            self.move_votes(Some(self.delegates_of(from)), Some(self.delegates_of(to)), value);
             ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), Transfer {
             // self.env().emit_event(Transfer {
                 from: Some(from),
@@ -361,150 +1004,344 @@ mod relp {
             Ok(())
         }
 
-        fn only_owner(&self) -> Result<()> {
-            let caller = self.env().caller();
-            if caller != self.owner {
-                return Err(Error::OnlyOwnerAccess)
+        /// Move `amount` votes between delegates, `None` mints or burns them from the total supply.
+        fn move_votes(&mut self, from: Option<AccountId>, to: Option<AccountId>, amount: Balance) {
+            if from == to || amount == 0 {
+                return
+            }
+            let supply = self.checkpoint_at(None, u32::MAX);
+            match from {
+                Some(delegate) => self.update_votes(delegate, |votes| votes - amount),
+                None => self.write_checkpoint(None, supply + amount),
+            }
+            match to {
+                Some(delegate) => self.update_votes(delegate, |votes| votes + amount),
+                None => self.write_checkpoint(None, supply - amount),
             }
-            Ok(())
         }
 
-        /// Contract owner.
-        #[ink(message)]
-        pub fn owner(&self) -> AccountId {
-            self.owner
+        fn update_votes(&mut self, delegate: AccountId, f: impl FnOnce(Balance) -> Balance) {
+            let previous_votes = self.checkpoint_at(Some(delegate), u32::MAX);
+            let new_votes = f(previous_votes);
+            self.write_checkpoint(Some(delegate), new_votes);
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), DelegateVotesChanged {
+                delegate,
+                previous_votes,
+                new_votes,
+            });
         }
 
-        /// transfer contract ownership to new owner.
-        #[ink(message)]
-        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
-            self.only_owner()?;
-            self.owner = new_owner;
-            Ok(())
+        /// Record `votes` of `owner` at the current block, one checkpoint per block.
+        fn write_checkpoint(&mut self, owner: Option<AccountId>, votes: Balance) {
+            let block_number = self.env().block_number();
+            let len = self.checkpoints_len.get(&owner).copied().unwrap_or(0);
+            if len > 0 {
+                if let Some(last) = self.checkpoints.get_mut(&(owner, len - 1)) {
+                    if last.from_block == block_number {
+                        last.votes = votes;
+                        return
+                    }
+                }
+            }
+            self.checkpoints.insert((owner, len), VoteCheckpoint { from_block: block_number, votes });
+            self.checkpoints_len.insert(owner, len + 1);
         }
 
-        // TODO: 此函数只用于redspot测试，因stable合约无法测试，在此增加接口用于获取ELC增发奖励
-        // TODO: 另外，elc增发的95％直接给本合约地址(详见stable合约TODO描述)，再由该合约地址按币天transfer给各relp持有者
-        // TODO: elc的增发奖励和elp的出块奖励能否共用同一个合同，都是使用币天计算，只是奖励来源不一样
-        // 后续删除此函数
-        #[ink(message)]
-        pub fn update_increase_awards(&mut self, elc_amount: u128) -> Result<()> {
-            self.only_owner()?;
-            let now_time = self.env().block_timestamp().into();
-            let (cur_total_coinday, last_time) = self.add_contract.total_coinday();
-            let total_supply = self.total_supply();
-            let increase_coinday = total_supply * (now_time - last_time);
-            let new_total_coinday = cur_total_coinday + increase_coinday;
-            // update total reward
-            let old_total_reward = self.add_contract.total_reward();
-            assert!(self.add_contract.update_total_reward(elc_amount + old_total_reward).is_ok());
-            // update total coinday
-            assert!(self.add_contract.update_total_coinday((new_total_coinday, now_time)).is_ok());
-            // let per_coinday = elc_amount * 1e12 as u128 / new_total_coinday;
-            // let new_value = (per_coinday, now_time);
-            assert!(self.add_contract.update_awards(elc_amount, new_total_coinday, now_time).is_ok());
-            Ok(())
+        /// Votes of `owner` at the end of block `block_number`.
+        fn checkpoint_at(&self, owner: Option<AccountId>, block_number: u32) -> Balance {
+            let len = self.checkpoints_len.get(&owner).copied().unwrap_or(0);
+            let from_block = |index: u32| self.checkpoints.get(&(owner, index)).map_or(0, |c| c.from_block);
+            latest_before(len, block_number, from_block)
+                .and_then(|index| self.checkpoints.get(&(owner, index)))
+                .map_or(0, |checkpoint| checkpoint.votes)
         }
 
-        /// Liquidate increase reward manually
-        #[ink(message)]
-        pub fn liquidate_increase_reward(&mut self, user: AccountId) {
-            let balance = self.balance_of(user);
-            assert!(balance > 0, "need balance > 0");
-            let coinday_info = self.add_contract.get_coinday_info(user);
-            let length = self.add_contract.awards_length() as usize;
-            let index = coinday_info.last_index as usize;
-            assert!(length > index, "Need to exist uncollected periods");
-
-            let (mut elc_amount, mut i) = (0, index);
-            while i < length {
-                if (i - index) >= 50 { break }
-                let cur_award = self.add_contract.get_award(i as u32);
-                // 计算截止每一期奖励时间点，用户的币天数
-                let coinday_i = coinday_info.amount + balance * (cur_award.timestamp - coinday_info.timestamp);
-                // TODO: 扩大了10**8，后续再考虑缩放
-                elc_amount += coinday_i * cur_award.amount * 1e8 as u128 / cur_award.total_coinday;
-                i += 1;    
+        fn only_locker(&self) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_locker(caller) {
+                return Err(Error::OnlyLockerAccess)
             }
+            Ok(caller)
+        }
 
-            // reward elc for user
-            if elc_amount > 0 {
-                let old_reward = self.add_contract.reward_of(user);
-                assert!(self.add_contract.update_rewards(user, elc_amount + old_reward).is_ok());
-                let cur_award = self.add_contract.get_award(i as u32);
-                self.increase_coinday_elp(user, cur_award.timestamp, i as u32);
+        fn escrow_power(escrow: &Escrow, now_time: u128) -> Balance {
+            if now_time >= escrow.end {
+                return 0
             }
+            escrow.amount * (escrow.end - now_time) / MAX_ESCROW_TIME
         }
 
-        fn get_elc_reward(&mut self, user: AccountId) -> Result<(u128, u32)> {
+        fn escrow_boost(escrow: &Escrow, now_time: u128) -> Balance {
+            Self::escrow_power(escrow, now_time) * MAX_ESCROW_BOOST / 10000
+        }
+
+        fn unlock_time(end: u128, now_time: u128) -> Result<u128> {
+            let end = end / ESCROW_EPOCH * ESCROW_EPOCH;
+            if end <= now_time || end > now_time + MAX_ESCROW_TIME {
+                return Err(Error::InvalidUnlockTime)
+            }
+            Ok(end)
+        }
+
+        fn active_escrow(&self, user: AccountId, now_time: u128) -> Result<Escrow> {
+            let escrow = self.escrows.get(&user).cloned().ok_or(Error::EscrowNotFound)?;
+            if now_time >= escrow.end {
+                return Err(Error::EscrowExpired)
+            }
+            Ok(escrow)
+        }
+
+        /// Sums of escrows not ended at `now_time`, `(slope, bias)`.
+        fn escrow_totals(&self, now_time: u128) -> Result<(Balance, u128)> {
+            self.totals_from(self.escrow_slope, self.escrow_bias, self.escrow_checkpoint, now_time)
+        }
+
+        /// Escrow sums at `checkpoint` brought to `now_time`, `(slope, bias)`.
+        fn totals_from(
+            &self,
+            mut slope: Balance,
+            mut bias: u128,
+            checkpoint: u128,
+            now_time: u128,
+        ) -> Result<(Balance, u128)> {
+            // 所有锁仓都在 checkpoint + MAX_ESCROW_TIME 之前到期
+            let last = core::cmp::min(now_time, checkpoint + MAX_ESCROW_TIME);
+            let mut epoch = checkpoint / ESCROW_EPOCH * ESCROW_EPOCH + ESCROW_EPOCH;
+            while epoch <= last {
+                let ended = self.slope_changes.get(&epoch).copied().unwrap_or(0);
+                slope = sub(slope, ended)?;
+                bias = sub(bias, mul(ended, epoch)?)?;
+                epoch += ESCROW_EPOCH;
+            }
+            Ok((slope, bias))
+        }
+
+        /// Replace the escrow of `user` with `escrow`, both active at `now_time`.
+        fn deposit_escrow(&mut self, user: AccountId, escrow: Escrow, now_time: u128) -> Result<()> {
+            let (mut slope, mut bias) = self.escrow_totals(now_time)?;
+            // 先完成全部计算再写入，出错时不留下部分更新
+            let mut old_change = None;
+            if let Some(old) = self.escrows.get(&user).cloned() {
+                slope = sub(slope, old.amount)?;
+                bias = sub(bias, mul(old.amount, old.end)?)?;
+                let ended = self.slope_changes.get(&old.end).copied().unwrap_or(0);
+                old_change = Some((old.end, sub(ended, old.amount)?));
+            }
+            let user_bias = mul(escrow.amount, escrow.end)?;
+            slope = add(slope, escrow.amount)?;
+            bias = add(bias, user_bias)?;
+            if let Some((end, ended)) = old_change {
+                self.slope_changes.insert(end, ended);
+            }
+            let ended = self.slope_changes.get(&escrow.end).copied().unwrap_or(0);
+            self.slope_changes.insert(escrow.end, ended + escrow.amount);
+            self.escrow_slope = slope;
+            self.escrow_bias = bias;
+            self.escrow_checkpoint = now_time;
+            self.write_escrow_point(None, slope, bias, now_time);
+            self.write_escrow_point(Some(user), escrow.amount, user_bias, now_time);
+
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), EscrowUpdated {
+                user,
+                amount: escrow.amount,
+                end: escrow.end,
+            });
+            self.escrows.insert(user, escrow);
+            Ok(())
+        }
+
+        /// Record the escrow sums of `owner` at the current block, one point per block.
+        fn write_escrow_point(&mut self, owner: Option<AccountId>, slope: Balance, bias: u128, timestamp: u128) {
+            let from_block = self.env().block_number();
+            let point = EscrowPoint { from_block, slope, bias, timestamp };
+            let len = self.escrow_points_len.get(&owner).copied().unwrap_or(0);
+            if len > 0 && self.escrow_points.get(&(owner, len - 1)).map(|p| p.from_block) == Some(from_block) {
+                self.escrow_points.insert((owner, len - 1), point);
+                return
+            }
+            self.escrow_points.insert((owner, len), point);
+            self.escrow_points_len.insert(owner, len + 1);
+        }
+
+        /// Escrow point of `owner` at the end of block `block_number`.
+        fn escrow_point_at(&self, owner: Option<AccountId>, block_number: u32) -> Option<EscrowPoint> {
+            let len = self.escrow_points_len.get(&owner).copied().unwrap_or(0);
+            let index = latest_before(len, block_number, |index| {
+                self.escrow_points.get(&(owner, index)).map_or(0, |p| p.from_block)
+            })?;
+            self.escrow_points.get(&(owner, index)).copied()
+        }
+
+        /// `sync_boost` when `user` has no more periods to liquidate, otherwise the boost is
+        /// refreshed by the liquidation that brings it within `max_collect_periods`.
+        fn refresh_boost(&mut self, user: AccountId) -> Result<()> {
+            if self.liquidations_needed(user) != (0, 0) {
+                return Ok(())
+            }
+            self.sync_boost(user)
+        }
+
+        /// Settle the reward streams of `user` with its current coinday balance,
+        /// then set its boost from its escrow.
+        fn sync_boost(&mut self, user: AccountId) -> Result<()> {
             let now_time = self.env().block_timestamp().into();
-            // calculate reward to mint elc
-            let balance = self.balance_of(user);
+            let new_boost = self.escrows.get(&user).map_or(0, |escrow| Self::escrow_boost(escrow, now_time));
+            let old_boost = self.boost_of(user);
+            if new_boost == old_boost {
+                return Ok(())
+            }
+            let coinday_balance = self.coinday_balance_of(user)?;
+            let (timestamp, index) = self.get_elc_reward(user)?;
+            Distribution::<Elc>::increase(&mut *self.add_contract, user, coinday_balance, timestamp, index)?;
+            let (_, index_elp) = self.get_elp_reward(user)?;
+            Distribution::<Elp>::increase(&mut *self.reward_contract, user, coinday_balance, timestamp, index_elp)?;
+            self.update_total_coinday(timestamp, 0, 0)?;
+
+            self.total_boost = add(sub(self.total_boost, old_boost)?, new_boost)?;
+            if new_boost == 0 {
+                self.boosts.take(&user);
+            } else {
+                self.boosts.insert(user, new_boost);
+            }
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), BoostUpdated {
+                user,
+                boost: new_boost,
+            });
+            Ok(())
+        }
+
+        fn is_active(&self, lock: &Lock) -> bool {
+            match lock.unlock {
+                UnlockCondition::Block(block) => self.env().block_number() < block,
+                UnlockCondition::Timestamp(timestamp) => {
+                    let now_time: u128 = self.env().block_timestamp().into();
+                    now_time < timestamp
+                }
+                UnlockCondition::Manual => true,
+            }
+        }
+
+        fn only_owner(&self) -> Result<()> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyOwnerAccess)
+            }
+            Ok(())
+        }
+
+        /// Contract owner.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// transfer contract ownership to new owner.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.only_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        // TODO: 此函数只用于redspot测试，因stable合约无法测试，在此增加接口用于获取ELC增发奖励
+        // TODO: 另外，elc增发的95％直接给本合约地址(详见stable合约TODO描述)，再由该合约地址按币天transfer给各relp持有者
+        // TODO: elc的增发奖励和elp的出块奖励能否共用同一个合同，都是使用币天计算，只是奖励来源不一样
+        // 后续删除此函数
+        #[ink(message)]
+        pub fn update_increase_awards(&mut self, elc_amount: u128) -> Result<()> {
+            self.only_owner()?;
+            let now_time = self.env().block_timestamp().into();
+            let coinday_supply = self.coinday_supply()?;
+            // update total reward, total coinday and period award
+            Distribution::<Elc>::publish(&mut *self.add_contract, elc_amount, coinday_supply, now_time)?;
+            Ok(())
+        }
+
+        /// Liquidate increase reward manually, the settled ELC is only recorded in additional
+        /// contract, nothing is minted.
+        #[ink(message)]
+        pub fn liquidate_increase_reward(&mut self, user: AccountId) -> Result<()> {
+            let balance = self.coinday_balance_of(user)?;
+            assert!(balance > 0, "need balance > 0");
             let coinday_info = self.add_contract.get_coinday_info(user);
-            let length = self.add_contract.awards_length() as usize;
-            let index = coinday_info.last_index as usize;
-            // TODO: 测试用，限制每次最多获取5 periods
-            if length - index > 5 && balance != 0 {
-                return Err(Error::NeedLiquidateIncreaseReward);
-            }
-            // 对于有奖励可领取者，限制每次最多只能领取50 periods
-            // if length - index > 50 && balance != 0 {
-            //     return Err(Error::NeedLiquidateBlockReward);
-            // }
+            let length = self.add_contract.awards_length();
+            assert!(length > coinday_info.last_index, "Need to exist uncollected periods");
 
-            let mut elc_amount = 0;
-            for i in index..length {
-                let cur_award = self.add_contract.get_award(i as u32);
-                // 计算截止每一期奖励时间点，用户的币天数
-                let coinday_i = coinday_info.amount + balance * (cur_award.timestamp - coinday_info.timestamp);
-                // TODO: 扩大了10**8，后续再考虑缩放
-                elc_amount += coinday_i * cur_award.amount * 1e8 as u128 / cur_award.total_coinday;
+            // reward elc for user
+            Distribution::<Elc>::settle(&mut *self.add_contract, user, balance, self.max_liquidate_periods)?;
+            self.refresh_boost(user)
+        }
+
+        /// Liquidate increase reward of many users in one call. Users without balance or
+        /// uncollected periods are skipped. The keeper gets `liquidation_bounty` of the
+        /// settled ELC. Returns the number of settled users.
+        #[ink(message)]
+        pub fn liquidate_increase_rewards(&mut self, users: Vec<AccountId>) -> Result<u32> {
+            if users.len() > MAX_LIQUIDATION_BATCH {
+                return Err(Error::BatchTooLarge)
+            }
+            let keeper = self.env().caller();
+            let length = self.add_contract.awards_length();
+            let (mut settled, mut bounty) = (0, 0);
+            for user in users {
+                let balance = self.coinday_balance_of(user)?;
+                let (elc_amount, user_bounty) = match liquidate_for::<_, Elc>(
+                    &mut *self.add_contract, user, keeper, balance, length,
+                    self.max_liquidate_periods, self.liquidation_bounty)? {
+                    Some(liquidated) => liquidated,
+                    None => continue,
+                };
+                if elc_amount > user_bounty {
+                    self.pay_elc_reward(user, elc_amount - user_bounty);
+                }
+                bounty += user_bounty;
+                settled += 1;
+                self.refresh_boost(user)?;
+            }
+            if bounty > 0 {
+                assert!(self.elc_contract.mint(keeper, bounty).is_ok());
             }
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), IncreaseRewardsLiquidated {
+                keeper,
+                settled,
+                bounty,
+            });
+            Ok(settled)
+        }
+
+        fn get_elc_reward(&mut self, user: AccountId) -> Result<(u128, u32)> {
+            let now_time = self.env().block_timestamp().into();
+            // calculate reward to mint elc
+            let balance = self.coinday_balance_of(user)?;
+            let (elc_amount, length) = collect::<_, Elc>(
+                &mut *self.add_contract, user, balance, self.max_collect_periods, Error::NeedLiquidateIncreaseReward)?;
 
             // mint elc for user
             if elc_amount > 0 {
-                let old_reward = self.add_contract.reward_of(user);
-                assert!(self.add_contract.update_rewards(user, elc_amount + old_reward).is_ok());
-                assert!(self.elc_contract.mint(user, elc_amount).is_ok());
+                self.pay_elc_reward(user, elc_amount);
             }
-            Ok((now_time, length as u32))
+            Ok((now_time, length))
         }
 
-        fn decrease_coinday_elc(
-            &mut self, 
-            user: AccountId, 
-            value: Balance, 
-            now_time: u128,
-            index: u32
-        ) -> u128 {
-            let balance = self.balance_of(user);
-            let coinday_info = self.add_contract.get_coinday_info(user);
-            // 先将币天更新到当前时间点
-            let cur_coinday = coinday_info.amount + balance * (now_time - coinday_info.timestamp);
-            // decrease amount = coinday of user * ( value / balance );
-            let decrease_coinday = cur_coinday * (value * 1e8 as u128 / balance) / 1e8 as u128; 
-            let new_coinday = cur_coinday - decrease_coinday;
-            assert!(self.add_contract.update_coindays(user, new_coinday, now_time, index).is_ok());
-            decrease_coinday
-        }
-
-        fn increase_coinday_elc(
-            &mut self, 
-            user: AccountId, 
-            now_time: u128,
-            index: u32
-        ) {
-            let balance = self.balance_of(user);
-            let coinday_info = self.add_contract.get_coinday_info(user);
-            let new_coinday = coinday_info.amount + balance * (now_time - coinday_info.timestamp);
-            assert!(self.add_contract.update_coindays(user, new_coinday, now_time, index).is_ok());
+        /// Mint collected ELC reward to user, or lock it into vesting in additional contract
+        /// when vesting is enabled there. Part of it may be covered by the exit penalties
+        /// additional contract holds, only the rest is minted.
+        fn pay_elc_reward(&mut self, user: AccountId, elc_amount: u128) {
+            if self.add_contract.vesting_config().enabled {
+                let covered = self.add_contract.vest(user, elc_amount).expect("Vest elc reward failed");
+                if elc_amount > covered {
+                    assert!(self.elc_contract.mint(self.add_accountid, elc_amount - covered).is_ok());
+                }
+            } else {
+                assert!(self.elc_contract.mint(user, elc_amount).is_ok());
+            }
         }
 
-        fn update_total_elc(&mut self, timestamp: u128, decrease: u128) {
-            let total_info = self.add_contract.total_coinday();
-            let increase_coinday = self.total_supply() * (timestamp - total_info.1);
-            let new_total_coinday = total_info.0 + increase_coinday - decrease;
-            assert!(self.add_contract.update_total_coinday((new_total_coinday, timestamp)).is_ok());
+        fn update_total_coinday(&mut self, timestamp: u128, decrease_elc: u128, decrease_elp: u128) -> Result<()> {
+            let coinday_supply = self.coinday_supply()?;
+            Distribution::<Elc>::update_total(&mut *self.add_contract, coinday_supply, timestamp, decrease_elc)?;
+            Distribution::<Elp>::update_total(&mut *self.reward_contract, coinday_supply, timestamp, decrease_elp)?;
+            Ok(())
         }
 
         #[ink(message)]
@@ -516,11 +1353,11 @@ mod relp {
             let now_time = self.env().block_timestamp().into();
 
             // TODO: 测试用，两次发奖间隔大于半小时
-            let mut epochs = (now_time - daily_award.1) / (1800*1000);
+            let mut epochs = sub(now_time, daily_award.1).map_err(|_| Error::IntervalTooShort)? / (1800*1000);
             if epochs <= 0 {
                 return Err(Error::IntervalTooShort)
             }
-            let new_timestamp = daily_award.1 + epochs * 1800*1000;
+            let new_timestamp = add(daily_award.1, mul(epochs, 1800*1000)?)?;
             // // 两次发奖的间隔需要大于一天
             // let mut epochs = (now_time - daily_award.1) / (3600*24*1000);
             // if epochs <= 0 {
@@ -530,57 +1367,115 @@ mod relp {
 
             let (mut new_daily_amount, mut period_award) = (daily_award.0, 0);
             while epochs > 0 {
-                period_award += new_daily_amount;
+                period_award = add(period_award, new_daily_amount)?;
                 new_daily_amount = new_daily_amount * 99 / 100;
                 epochs -= 1;
             }
             // update daily award infos.
             assert!(self.reward_contract.update_daily_award((new_daily_amount, new_timestamp)).is_ok());
 
-            let elp_amount = period_award;
-            let (cur_total_coinday, last_time) = self.reward_contract.total_coinday();
-            let total_supply = self.total_supply();
-            let increase_coinday = total_supply * (now_time - last_time);
-            let new_total_coinday = cur_total_coinday + increase_coinday;
+            // update total reward, total coinday and period award
+            let coinday_supply = self.coinday_supply()?;
+            Distribution::<Elp>::publish(&mut *self.reward_contract, period_award, coinday_supply, now_time)?;
+            Ok(())
+        }
 
-            // update total reward
-            let old_total_reward = self.reward_contract.total_reward();
-            assert!(self.reward_contract.update_total_reward(elp_amount + old_total_reward).is_ok());
+        /// Liquidate block reward manually
+        #[ink(message)]
+        pub fn liquidate_block_reward(&mut self, user: AccountId) -> Result<()> {
+            let balance = self.coinday_balance_of(user)?;
+            let coinday_info = self.reward_contract.get_coinday_info(user);
+            let length = self.reward_contract.awards_length();
+            assert!(length > coinday_info.last_index, "Need to exist uncollected periods");
+            Distribution::<Elp>::settle(&mut *self.reward_contract, user, balance, self.max_liquidate_periods)?;
+            self.refresh_boost(user)
+        }
 
-            // update total coinday
-            assert!(self.reward_contract.update_total_coinday((new_total_coinday, now_time)).is_ok());
+        /// Liquidate block reward of many users in one call. Users without balance or
+        /// uncollected periods are skipped. The keeper is credited `liquidation_bounty` of the settled ELP in reward
+        /// contract. Returns the number of settled users.
+        #[ink(message)]
+        pub fn liquidate_block_rewards(&mut self, users: Vec<AccountId>) -> Result<u32> {
+            if users.len() > MAX_LIQUIDATION_BATCH {
+                return Err(Error::BatchTooLarge)
+            }
+            let keeper = self.env().caller();
+            let length = self.reward_contract.awards_length();
+            let (mut settled, mut bounty) = (0, 0);
+            for user in users {
+                let balance = self.coinday_balance_of(user)?;
+                let (_, user_bounty) = match liquidate_for::<_, Elp>(
+                    &mut *self.reward_contract, user, keeper, balance, length,
+                    self.max_liquidate_periods, self.liquidation_bounty)? {
+                    Some(liquidated) => liquidated,
+                    None => continue,
+                };
+                bounty += user_bounty;
+                settled += 1;
+                self.refresh_boost(user)?;
+            }
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), BlockRewardsLiquidated {
+                keeper,
+                settled,
+                bounty,
+            });
+            Ok(settled)
+        }
 
-            // update period award
-            assert!(self.reward_contract.update_awards(elp_amount, new_total_coinday, now_time).is_ok());
+        /// Keeper bounty of batch liquidation, base 10000.
+        #[ink(message)]
+        pub fn liquidation_bounty(&self) -> u128 {
+            self.liquidation_bounty
+        }
+
+        #[ink(message)]
+        pub fn set_liquidation_bounty(&mut self, rate: u128) -> Result<()> {
+            self.only_owner()?;
+            if rate > MAX_LIQUIDATION_BOUNTY {
+                return Err(Error::InvalidBountyRate)
+            }
+            self.liquidation_bounty = rate;
             Ok(())
         }
 
-        /// Liquidate block reward manually
+        /// Returns `(max_collect_periods, max_liquidate_periods)`.
         #[ink(message)]
-        pub fn liquidate_block_reward(&mut self, user: AccountId) {
-            let balance = self.balance_of(user);
-            let coinday_info = self.reward_contract.get_coinday_info(user);
-            let length = self.reward_contract.awards_length() as usize;
-            let index = coinday_info.last_index as usize;
-            assert!(length > index, "Need to exist uncollected periods");
+        pub fn settlement_limits(&self) -> (u32, u32) {
+            (self.max_collect_periods, self.max_liquidate_periods)
+        }
 
-            let (mut elp_amount, mut i) = (0, index);
-            while i < length {
-                if (i - index) >= 50 { break }
-                let cur_award = self.reward_contract.get_award(i as u32);
-                // 计算截止每一期奖励时间点，用户的币天数
-                let coinday_i = coinday_info.amount + balance * (cur_award.timestamp - coinday_info.timestamp);
-                // 原日奖励已经扩大1e8，此处不用再扩大
-                elp_amount += coinday_i * cur_award.amount / cur_award.total_coinday;
-                i += 1;    
+        #[ink(message)]
+        pub fn set_settlement_limits(&mut self, max_collect_periods: u32, max_liquidate_periods: u32) -> Result<()> {
+            self.only_owner()?;
+            if max_collect_periods == 0 || max_liquidate_periods == 0
+                || max_collect_periods > MAX_SETTLEMENT_PERIODS
+                || max_liquidate_periods > MAX_SETTLEMENT_PERIODS {
+                return Err(Error::InvalidSettlementLimit)
             }
+            self.max_collect_periods = max_collect_periods;
+            self.max_liquidate_periods = max_liquidate_periods;
+            ::ink_lang::codegen::EmitEvent::<RELP>::emit_event(Self::env(), SettlementLimitsUpdated {
+                max_collect_periods,
+                max_liquidate_periods,
+            });
+            Ok(())
+        }
 
-            if elp_amount > 0 {
-                let old_reward = self.reward_contract.reward_of(user);
-                assert!(self.reward_contract.update_rewards(user, elp_amount + old_reward).is_ok());
-                let cur_award = self.reward_contract.get_award(i as u32);
-                self.increase_coinday_elp(user, cur_award.timestamp, i as u32);
+        /// Liquidation calls `user` needs before it can transfer, mint or burn again.
+        /// Returns `(increase_reward_calls, block_reward_calls)`.
+        #[ink(message)]
+        pub fn liquidations_needed(&self, user: AccountId) -> (u32, u32) {
+            if matches!(self.coinday_balance_of(user), Ok(0)) {
+                return (0, 0)
             }
+            let elc_periods = self.add_contract.awards_length()
+                .saturating_sub(self.add_contract.get_coinday_info(user).last_index);
+            let elp_periods = self.reward_contract.awards_length()
+                .saturating_sub(self.reward_contract.get_coinday_info(user).last_index);
+            (
+                liquidations_for(elc_periods, self.max_collect_periods, self.max_liquidate_periods),
+                liquidations_for(elp_periods, self.max_collect_periods, self.max_liquidate_periods),
+            )
         }
 
         fn get_elp_reward(&mut self, user: AccountId) -> Result<(u128, u32)> { 
@@ -592,74 +1487,108 @@ mod relp {
             if total_supply == 0 && deploy_time == daily_award.1 {
                 assert!(self.reward_contract.update_daily_award((daily_award.0, now_time)).is_ok());
             }
-            // calculate reward to mint elp
-            let balance = self.balance_of(user);
-            let coinday_info = self.reward_contract.get_coinday_info(user);
-            let length = self.reward_contract.awards_length() as usize;
-            let index = coinday_info.last_index as usize;
+            // calculate reward of elp, the rewards are recorded in reward contract
+            let balance = self.coinday_balance_of(user)?;
+            let (_, length) = collect::<_, Elp>(
+                &mut *self.reward_contract, user, balance, self.max_collect_periods, Error::NeedLiquidateBlockReward)?;
+            Ok((now_time, length))
+        }
+    }
 
-            // TODO: 测试用，限制每次最多获取5 periods
-            if length - index > 5 && balance != 0 {
-                return Err(Error::NeedLiquidateIncreaseReward);
+    /// Index of the last of `len` checkpoints starting at or before `block_number`,
+    /// `from_block` gives the start block of a checkpoint.
+    fn latest_before(len: u32, block_number: u32, from_block: impl Fn(u32) -> u32) -> Option<u32> {
+        if len == 0 || from_block(0) > block_number {
+            return None
+        }
+        // 二分查找 block_number 之前最近的检查点
+        let (mut low, mut high) = (0, len - 1);
+        while low < high {
+            let mid = high - (high - low) / 2;
+            if from_block(mid) <= block_number {
+                low = mid;
+            } else {
+                high = mid - 1;
             }
-            // 对于有奖励可领取者，限制每次最多只能领取50 periods
-            // if length - index > 50 && balance != 0 {
-            //     return Err(Error::NeedLiquidateBlockReward);
-            // }
+        }
+        Some(low)
+    }
 
-            let mut elp_amount = 0;
-            for i in index..length {
-                let cur_award = self.reward_contract.get_award(i as u32);
-                // 计算截止每一期奖励时间点，用户的币天数
-                let coinday_i = coinday_info.amount + balance * (cur_award.timestamp - coinday_info.timestamp);
-                // 原日奖励已经扩大1e8，此处不用再扩大
-                elp_amount += coinday_i * cur_award.amount / cur_award.total_coinday;
-            }
+    /// Voting weight of escrow sums `(slope, bias)` at `time`.
+    fn point_power(slope: Balance, bias: u128, time: u128) -> Result<Balance> {
+        Ok(sub(bias, mul(slope, time)?)? / MAX_ESCROW_TIME)
+    }
 
-            // reward elp for user
-            if elp_amount > 0 {
-                let old_reward = self.reward_contract.reward_of(user);
-                assert!(self.reward_contract.update_rewards(user, elp_amount + old_reward).is_ok());
-            }
-            Ok((now_time, length as u32))
+    /// Move the keeper bounty out of the reward just credited to `user`. Returns the bounty.
+    fn take_bounty<L: CoindayLedger>(
+        ledger: &mut L,
+        user: AccountId,
+        keeper: AccountId,
+        amount: u128,
+        rate: u128,
+    ) -> Result<u128> {
+        let bounty = mul_div(amount, rate, 10000)?;
+        if bounty == 0 || user == keeper {
+            return Ok(bounty)
         }
+        let user_reward = ledger.reward_balance(user);
+        ledger.set_reward_balance(user, sub(user_reward, bounty)?);
+        let keeper_reward = ledger.reward_balance(keeper);
+        ledger.set_reward_balance(keeper, add(keeper_reward, bounty)?);
+        Ok(bounty)
+    }
 
-        fn decrease_coinday_elp(
-            &mut self, 
-            user: AccountId, 
-            value: Balance, 
-            now_time: u128,
-            index: u32
-        ) -> u128 {
-            let balance = self.balance_of(user);
-            let coinday_info = self.reward_contract.get_coinday_info(user);
-            // 先将币天更新到当前时间点
-            let cur_coinday = coinday_info.amount + balance * (now_time - coinday_info.timestamp);
-            // decrease amount = coinday of user * ( value / balance );
-            let decrease_coinday = cur_coinday * (value * 1e8 as u128 / balance) / 1e8 as u128; 
-            let new_coinday = cur_coinday - decrease_coinday;
-            assert!(self.reward_contract.update_coindays(user, new_coinday, now_time, index).is_ok());
-            decrease_coinday
-        }
-
-        fn increase_coinday_elp(
-            &mut self, 
-            user: AccountId, 
-            now_time: u128,
-            index: u32
-        ) {
-            let balance = self.balance_of(user);
-            let coinday_info = self.reward_contract.get_coinday_info(user);
-            let new_coinday = coinday_info.amount + balance * (now_time - coinday_info.timestamp);
-            assert!(self.reward_contract.update_coindays(user, new_coinday, now_time, index).is_ok());
+    /// Settle at most `max_periods` uncollected periods of `user` for `keeper`, who gets `rate`
+    /// of the settled amount. `None` if the user has no balance or nothing to collect of the
+    /// `length` periods. Returns the settled amount and the bounty.
+    fn liquidate_for<L: CoindayLedger, A: RewardAsset>(
+        ledger: &mut L,
+        user: AccountId,
+        keeper: AccountId,
+        balance: Balance,
+        length: u32,
+        max_periods: u32,
+        rate: u128,
+    ) -> Result<Option<(u128, u128)>> {
+        if balance == 0 || length <= ledger.coinday_of(user).last_index {
+            return Ok(None)
+        }
+        let (amount, _) = Distribution::<A>::settle(ledger, user, balance, max_periods)?;
+        let bounty = take_bounty(ledger, user, keeper, amount, rate)?;
+        Ok(Some((amount, bounty)))
+    }
+
+    /// Credit the uncollected periods of `user` in a reward stream before its coinday changes.
+    /// Returns the credited amount and the index of the next period.
+    fn collect<L: CoindayLedger, A: RewardAsset>(
+        ledger: &mut L,
+        user: AccountId,
+        balance: Balance,
+        max_periods: u32,
+        too_many_periods: Error,
+    ) -> Result<(u128, u32)> {
+        let coinday_info = ledger.coinday_of(user);
+        let length = ledger.awards_len();
+        // 对于有奖励可领取者，限制每次最多只能领取max_periods个periods
+        if length - coinday_info.last_index > max_periods && balance != 0 {
+            return Err(too_many_periods);
         }
+        Distribution::<A>::settle(ledger, user, balance, length).map_err(Error::from)
+    }
 
-        fn update_total_elp(&mut self, timestamp: u128, decrease: u128) {
-            let total_info = self.reward_contract.total_coinday();
-            let increase_coinday = self.total_supply() * (timestamp - total_info.1);
-            let new_total_coinday = total_info.0 + increase_coinday - decrease;
-            assert!(self.reward_contract.update_total_coinday((new_total_coinday, timestamp)).is_ok());
+    /// Fee of `rate`, base 10000, on `value`.
+    fn fee_for(value: Balance, rate: u128) -> Result<Balance> {
+        Ok(mul_div(value, rate, 10000)?)
+    }
+
+    /// Liquidation calls needed to bring `periods` uncollected periods within `max_collect`,
+    /// each call settling `max_liquidate` of them.
+    fn liquidations_for(periods: u32, max_collect: u32, max_liquidate: u32) -> u32 {
+        if periods <= max_collect {
+            return 0
         }
+        let excess = periods - max_collect;
+        (excess + max_liquidate - 1) / max_liquidate
     }
 
     /// Unit tests.
@@ -670,12 +1599,78 @@ mod relp {
         use ink_lang as ink;
         use ink_env::{test, call, account_id, DefaultEnvironment};
 
+        use coinday::{Award, Coinday};
+        use ink_prelude::collections::BTreeMap;
+
         type Accounts = test::DefaultAccounts<DefaultEnvironment>;
 
         fn default_accounts() -> Accounts {
             test::default_accounts().expect("Cannot get accounts.") 
         }
 
+        /// In-memory reward stream, stands in for a reward contract reference.
+        #[derive(Default)]
+        struct MockLedger {
+            total_reward: u128,
+            rewards: BTreeMap<AccountId, u128>,
+            total_coinday: (u128, u128),
+            coindays: BTreeMap<AccountId, Coinday>,
+            awards: Vec<Award>,
+        }
+
+        impl CoindayLedger for MockLedger {
+            fn coinday_of(&self, user: AccountId) -> Coinday {
+                self.coindays.get(&user).cloned().unwrap_or(Coinday {
+                    amount: 0,
+                    timestamp: 0,
+                    last_index: self.awards.len() as u32,
+                })
+            }
+            fn award_at(&self, index: u32) -> Option<Award> {
+                self.awards.get(index as usize).cloned()
+            }
+            fn awards_len(&self) -> u32 {
+                self.awards.len() as u32
+            }
+            fn stream_coinday(&self) -> (u128, u128) {
+                self.total_coinday
+            }
+            fn stream_reward(&self) -> u128 {
+                self.total_reward
+            }
+            fn reward_balance(&self, user: AccountId) -> u128 {
+                self.rewards.get(&user).copied().unwrap_or(0)
+            }
+            fn set_coinday(&mut self, user: AccountId, info: Coinday) {
+                self.coindays.insert(user, info);
+            }
+            fn remove_coinday(&mut self, user: AccountId) {
+                self.coindays.remove(&user);
+            }
+            fn set_stream_coinday(&mut self, new_value: (u128, u128)) {
+                self.total_coinday = new_value;
+            }
+            fn set_stream_reward(&mut self, new_value: u128) {
+                self.total_reward = new_value;
+            }
+            fn set_reward_balance(&mut self, user: AccountId, value: u128) {
+                self.rewards.insert(user, value);
+            }
+            fn push_award(&mut self, award: Award) {
+                self.awards.push(award);
+            }
+        }
+
+        /// Ledger with `periods` awards of 10 ELP, each fully owned by a holder of 100 since time 0.
+        fn ledger_with_periods(holder: AccountId, periods: u32) -> MockLedger {
+            let mut ledger = MockLedger::default();
+            for i in 1..=periods as u128 {
+                ledger.push_award(Award { amount: 10, total_coinday: 100 * i, timestamp: i });
+            }
+            ledger.set_coinday(holder, Coinday { amount: 0, timestamp: 0, last_index: 0 });
+            ledger
+        }
+
         /// The default constructor does its job.
         #[ink::test]
         fn new_works() {
@@ -693,7 +1688,8 @@ mod relp {
             assert_eq!(relp.token_decimals().unwrap_or(0), 8);
             assert_eq!(relp.total_supply(), 0);
             assert_eq!(relp.balance_of(accounts.alice), 0);
-            assert_eq!(relp.lock_info_of(accounts.alice), (0, 0));
+            assert_eq!(relp.locks_of(accounts.alice), Vec::new());
+            assert_eq!(relp.free_balance_of(accounts.alice), 0);
             assert_eq!(relp.owner(), accounts.alice);
         }
 
@@ -720,6 +1716,366 @@ mod relp {
             assert_eq!(relp.allowance(accounts.alice, accounts.bob), 66);
         }
 
+        fn set_caller(caller: AccountId) {
+            let callee = account_id::<DefaultEnvironment>();
+            let data = test::CallData::new(call::Selector::new([0x00; 4]));
+            test::push_execution_context::<DefaultEnvironment>(
+                caller,
+                callee,
+                1000000,
+                1000000,
+                data,
+            );
+        }
+
+        #[ink::test]
+        fn set_lock_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            assert!(relp.grant_locker(accounts.alice).is_ok());
+            assert!(relp.set_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Block(10)).is_ok());
+            assert!(relp.set_lock(accounts.bob, *b"gov-vote", 50, UnlockCondition::Manual).is_ok());
+            // expired lock does not count.
+            assert!(relp.set_lock(accounts.bob, *b"expired0", 80, UnlockCondition::Timestamp(0)).is_ok());
+            assert_eq!(relp.locks_of(accounts.bob).len(), 3);
+            assert_eq!(relp.locked_balance_of(accounts.bob), 50);
+            assert_eq!(
+                relp.lock_of(accounts.bob, accounts.alice, *b"proposal"),
+                Some(Lock { locker: accounts.alice, id: *b"proposal", amount: 30, unlock: UnlockCondition::Block(10) }));
+        }
+
+        #[ink::test]
+        fn set_lock_failed() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            assert_eq!(
+                relp.set_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Manual),
+                Err(Error::OnlyLockerAccess));
+            assert!(relp.grant_locker(accounts.alice).is_ok());
+            assert!(relp.set_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Manual).is_ok());
+            assert_eq!(
+                relp.set_lock(accounts.bob, *b"proposal", 40, UnlockCondition::Manual),
+                Err(Error::LockAlreadyExists));
+        }
+
+        #[ink::test]
+        fn extend_lock_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            assert!(relp.grant_locker(accounts.alice).is_ok());
+            assert!(relp.set_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Block(10)).is_ok());
+            assert_eq!(
+                relp.extend_lock(accounts.bob, *b"proposal", 20, UnlockCondition::Block(10)),
+                Err(Error::InvalidLockExtension));
+            assert_eq!(
+                relp.extend_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Block(9)),
+                Err(Error::InvalidLockExtension));
+            assert_eq!(
+                relp.extend_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Timestamp(100)),
+                Err(Error::InvalidLockExtension));
+            assert!(relp.extend_lock(accounts.bob, *b"proposal", 60, UnlockCondition::Block(20)).is_ok());
+            assert_eq!(relp.locked_balance_of(accounts.bob), 60);
+        }
+
+        #[ink::test]
+        fn release_lock_is_scoped_to_locker() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            assert!(relp.grant_locker(accounts.alice).is_ok());
+            assert!(relp.grant_locker(accounts.charlie).is_ok());
+            assert!(relp.set_lock(accounts.bob, *b"proposal", 30, UnlockCondition::Manual).is_ok());
+
+            set_caller(accounts.charlie);
+            assert_eq!(relp.release_lock(accounts.bob, *b"proposal"), Err(Error::LockNotFound));
+            assert_eq!(
+                relp.extend_lock(accounts.bob, *b"proposal", 40, UnlockCondition::Manual),
+                Err(Error::LockNotFound));
+
+            set_caller(accounts.alice);
+            assert!(relp.release_lock(accounts.bob, *b"proposal").is_ok());
+            assert_eq!(relp.locked_balance_of(accounts.bob), 0);
+            assert_eq!(relp.locks_of(accounts.bob), Vec::new());
+        }
+
+        #[ink::test]
+        fn escrow_totals_follow_unlock_times() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            let now = relp.escrow_checkpoint;
+            let end_a = (now / ESCROW_EPOCH + 2) * ESCROW_EPOCH;
+            let end_b = (now / ESCROW_EPOCH + 4) * ESCROW_EPOCH;
+            assert!(relp.deposit_escrow(accounts.alice, Escrow { amount: 100, end: end_a }, now).is_ok());
+            assert!(relp.deposit_escrow(accounts.bob, Escrow { amount: 300, end: end_b }, now).is_ok());
+            assert_eq!(relp.escrow_totals(now), Ok((400, 100 * end_a + 300 * end_b)));
+            assert_eq!(relp.escrow_totals(end_a), Ok((300, 300 * end_b)));
+            assert_eq!(relp.escrow_totals(end_b), Ok((0, 0)));
+
+            // extend alice to end_b and add 50.
+            assert!(relp.deposit_escrow(accounts.alice, Escrow { amount: 150, end: end_b }, now).is_ok());
+            assert_eq!(relp.escrow_totals(end_a), Ok((450, 450 * end_b)));
+            assert_eq!(relp.escrow_totals(end_b), Ok((0, 0)));
+        }
+
+        #[ink::test]
+        fn escrow_totals_failed_with_underflow() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let now = relp.escrow_checkpoint;
+            let end = (now / ESCROW_EPOCH + 2) * ESCROW_EPOCH;
+            // slope ending at `end` without any escrow behind it.
+            relp.slope_changes.insert(end, 100);
+            assert_eq!(relp.escrow_totals(now), Ok((0, 0)));
+            assert_eq!(relp.escrow_totals(end), Err(Error::Underflow));
+            assert_eq!(point_power(100, 100 * end, end + 1), Err(Error::Underflow));
+            assert_eq!(point_power(100, 100 * end, end), Ok(0));
+        }
+
+        #[ink::test]
+        fn escrow_power_is_snapshotted_per_block() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            let now = relp.escrow_checkpoint;
+            let end_a = (now / ESCROW_EPOCH + 2) * ESCROW_EPOCH;
+            let end_b = (now / ESCROW_EPOCH + 4) * ESCROW_EPOCH;
+            let start = ink_env::block_number::<DefaultEnvironment>();
+            assert!(relp.deposit_escrow(accounts.alice, Escrow { amount: MAX_ESCROW_TIME, end: end_a }, now).is_ok());
+
+            test::advance_block::<DefaultEnvironment>().expect("Cannot advance block");
+            assert!(relp.deposit_escrow(accounts.bob, Escrow { amount: MAX_ESCROW_TIME, end: end_b }, now).is_ok());
+            assert!(relp.deposit_escrow(accounts.alice, Escrow { amount: 2 * MAX_ESCROW_TIME, end: end_a }, now).is_ok());
+
+            assert_eq!(relp.voting_power_at(accounts.alice, start, now), end_a - now);
+            assert_eq!(relp.voting_power_at(accounts.alice, start + 1, now), 2 * (end_a - now));
+            assert_eq!(relp.voting_power_at(accounts.bob, start, now), 0);
+            assert_eq!(relp.voting_power_at(accounts.alice, start + 1, end_a), 0);
+            assert_eq!(relp.total_voting_power_at(start, now), Ok(end_a - now));
+            assert_eq!(relp.total_voting_power_at(start + 1, now), Ok(2 * (end_a - now) + end_b - now));
+            // alice's escrow has ended at end_a.
+            assert_eq!(relp.total_voting_power_at(start + 1, end_a), Ok(end_b - end_a));
+        }
+
+        #[ink::test]
+        fn escrow_power_decays_linearly() {
+            let escrow = Escrow { amount: 1000, end: MAX_ESCROW_TIME };
+            assert_eq!(RELP::escrow_power(&escrow, 0), 1000);
+            assert_eq!(RELP::escrow_power(&escrow, MAX_ESCROW_TIME / 2), 500);
+            assert_eq!(RELP::escrow_power(&escrow, MAX_ESCROW_TIME), 0);
+            assert_eq!(RELP::escrow_boost(&escrow, 0), 1500);
+            assert_eq!(RELP::escrow_boost(&escrow, MAX_ESCROW_TIME / 2), 750);
+        }
+
+        #[ink::test]
+        fn unlock_time_works() {
+            assert_eq!(RELP::unlock_time(ESCROW_EPOCH * 3 + 5, ESCROW_EPOCH), Ok(ESCROW_EPOCH * 3));
+            assert_eq!(RELP::unlock_time(ESCROW_EPOCH + 5, ESCROW_EPOCH), Err(Error::InvalidUnlockTime));
+            assert_eq!(
+                RELP::unlock_time(ESCROW_EPOCH * 2 + MAX_ESCROW_TIME, ESCROW_EPOCH),
+                Err(Error::InvalidUnlockTime));
+        }
+
+        #[ink::test]
+        fn delegate_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            relp.balances.insert(accounts.alice, 100);
+            relp.move_votes(None, Some(accounts.alice), 100);
+            assert_eq!(relp.votes_of(accounts.alice), 100);
+            assert_eq!(relp.total_supply_at(0), 100);
+
+            assert!(relp.delegate(accounts.bob).is_ok());
+            assert_eq!(relp.delegates_of(accounts.alice), accounts.bob);
+            assert_eq!(relp.votes_of(accounts.alice), 0);
+            assert_eq!(relp.votes_of(accounts.bob), 100);
+
+            // take the votes back.
+            assert!(relp.delegate(accounts.alice).is_ok());
+            assert_eq!(relp.delegates_of(accounts.alice), accounts.alice);
+            assert_eq!(relp.votes_of(accounts.alice), 100);
+            assert_eq!(relp.votes_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn votes_are_checkpointed_per_block() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            relp.move_votes(None, Some(accounts.alice), 100);
+            relp.move_votes(None, Some(accounts.alice), 50);
+            // the same block keeps one checkpoint.
+            assert_eq!(relp.checkpoints_of(accounts.alice, 1), None);
+
+            test::advance_block::<DefaultEnvironment>().expect("Cannot advance block");
+            relp.move_votes(Some(accounts.alice), Some(accounts.bob), 30);
+            test::advance_block::<DefaultEnvironment>().expect("Cannot advance block");
+            relp.move_votes(Some(accounts.alice), None, 20);
+
+            let start = relp.checkpoints_of(accounts.alice, 0).unwrap().from_block;
+            assert_eq!(relp.votes_at(accounts.alice, start), 150);
+            assert_eq!(relp.votes_at(accounts.alice, start + 1), 120);
+            assert_eq!(relp.votes_at(accounts.alice, start + 2), 100);
+            assert_eq!(relp.votes_at(accounts.bob, start), 0);
+            assert_eq!(relp.votes_at(accounts.bob, start + 2), 30);
+            assert_eq!(relp.total_supply_at(start + 1), 150);
+            assert_eq!(relp.total_supply_at(start + 2), 130);
+        }
+
+        #[ink::test]
+        fn liquidate_for_pays_keeper_bounty() {
+            let accounts = default_accounts();
+            let mut ledger = ledger_with_periods(accounts.alice, 3);
+            // bob liquidates alice at a 10% bounty.
+            assert_eq!(
+                liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.bob, 100, 3, 50, 1000),
+                Ok(Some((30, 3))));
+            assert_eq!(ledger.reward_balance(accounts.alice), 27);
+            assert_eq!(ledger.reward_balance(accounts.bob), 3);
+            assert_eq!(ledger.coinday_of(accounts.alice).last_index, 3);
+
+            // nothing left to collect.
+            assert_eq!(liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.bob, 100, 3, 50, 1000), Ok(None));
+        }
+
+        #[ink::test]
+        fn liquidate_for_keeps_bounty_of_self_liquidation() {
+            let accounts = default_accounts();
+            let mut ledger = ledger_with_periods(accounts.alice, 3);
+            assert_eq!(
+                liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.alice, 100, 3, 50, 1000),
+                Ok(Some((30, 3))));
+            assert_eq!(ledger.reward_balance(accounts.alice), 30);
+        }
+
+        #[ink::test]
+        fn liquidate_for_skips_users_without_balance() {
+            let accounts = default_accounts();
+            let mut ledger = ledger_with_periods(accounts.alice, 3);
+            assert_eq!(liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.bob, 0, 3, 50, 1000), Ok(None));
+            assert_eq!(ledger.coinday_of(accounts.alice).last_index, 0);
+            assert_eq!(ledger.reward_balance(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn liquidate_for_settles_at_most_max_periods() {
+            let accounts = default_accounts();
+            let mut ledger = ledger_with_periods(accounts.alice, 60);
+            assert_eq!(
+                liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.bob, 100, 60, 50, 0),
+                Ok(Some((500, 0))));
+            assert_eq!(ledger.coinday_of(accounts.alice).last_index, 50);
+            assert_eq!(
+                liquidate_for::<_, Elp>(&mut ledger, accounts.alice, accounts.bob, 100, 60, 50, 0),
+                Ok(Some((100, 0))));
+            assert_eq!(ledger.coinday_of(accounts.alice).last_index, 60);
+            assert_eq!(ledger.reward_balance(accounts.alice), 600);
+        }
+
+        #[ink::test]
+        fn set_liquidation_bounty_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            assert_eq!(relp.liquidation_bounty(), 10);
+            assert!(relp.set_liquidation_bounty(50).is_ok());
+            assert_eq!(relp.liquidation_bounty(), 50);
+            assert_eq!(relp.set_liquidation_bounty(101), Err(Error::InvalidBountyRate));
+        }
+
+        #[ink::test]
+        fn set_settlement_limits_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            assert_eq!(relp.settlement_limits(), (50, 50));
+            assert!(relp.set_settlement_limits(20, 80).is_ok());
+            assert_eq!(relp.settlement_limits(), (20, 80));
+            assert_eq!(relp.set_settlement_limits(0, 80), Err(Error::InvalidSettlementLimit));
+            assert_eq!(relp.set_settlement_limits(50, MAX_SETTLEMENT_PERIODS + 1), Err(Error::InvalidSettlementLimit));
+        }
+
+        #[ink::test]
+        fn set_transfer_fee_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            let stable = AccountId::from([0x4; 32]);
+            // no fee until the recipient is set.
+            assert!(relp.set_transfer_fee(30, None).is_ok());
+            assert_eq!(relp.transfer_fee_of(accounts.alice, accounts.bob, 10000), Ok(0));
+
+            assert!(relp.set_transfer_fee(30, Some(stable)).is_ok());
+            assert_eq!(relp.transfer_fee(), (30, Some(stable)));
+            assert_eq!(relp.transfer_fee_of(accounts.alice, accounts.bob, 10000), Ok(30));
+            // transfers to the recipient itself are free.
+            assert_eq!(relp.transfer_fee_of(accounts.alice, stable, 10000), Ok(0));
+            assert_eq!(relp.set_transfer_fee(101, Some(stable)), Err(Error::InvalidTransferFee));
+        }
+
+        #[ink::test]
+        fn fee_exempt_works() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let accounts = default_accounts();
+            assert!(relp.set_transfer_fee(30, Some(AccountId::from([0x4; 32]))).is_ok());
+            assert!(relp.set_fee_exempt(accounts.bob, true).is_ok());
+            assert!(relp.is_fee_exempt(accounts.bob));
+            assert_eq!(relp.transfer_fee_of(accounts.alice, accounts.bob, 10000), Ok(0));
+            assert_eq!(relp.transfer_fee_of(accounts.bob, accounts.alice, 10000), Ok(0));
+            assert!(relp.set_fee_exempt(accounts.bob, false).is_ok());
+            assert_eq!(relp.transfer_fee_of(accounts.alice, accounts.bob, 10000), Ok(30));
+        }
+
+        #[ink::test]
+        fn liquidations_for_works() {
+            assert_eq!(liquidations_for(0, 5, 50), 0);
+            assert_eq!(liquidations_for(5, 5, 50), 0);
+            // one call brings 6 periods down to 0.
+            assert_eq!(liquidations_for(6, 5, 50), 1);
+            assert_eq!(liquidations_for(55, 5, 50), 1);
+            assert_eq!(liquidations_for(56, 5, 50), 2);
+        }
+
+        #[ink::test]
+        fn liquidate_rewards_failed_when_batch_too_large() {
+            let mut relp = RELP::new(
+                AccountId::from([0x1; 32]), 
+                AccountId::from([0x2; 32]), 
+                AccountId::from([0x3; 32]));
+            let users = vec![AccountId::from([0x4; 32]); MAX_LIQUIDATION_BATCH + 1];
+            assert_eq!(relp.liquidate_increase_rewards(users.clone()), Err(Error::BatchTooLarge));
+            assert_eq!(relp.liquidate_block_rewards(users), Err(Error::BatchTooLarge));
+        }
+
         #[ink::test]
         fn transfer_ownership_works() {
             let mut relp = RELP::new(
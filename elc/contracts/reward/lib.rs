@@ -2,44 +2,209 @@
 
 pub use self::reward::{Reward, RewardRef};
 use ink_lang as ink;
+use ink_env::AccountId;
+use coinday::{Award, Coinday, CoindayLedger};
+
+/// Lets `coinday::Distribution` drive this stream through a contract reference.
+impl CoindayLedger for RewardRef {
+    fn coinday_of(&self, user: AccountId) -> Coinday {
+        self.get_coinday_info(user)
+    }
+
+    fn award_at(&self, index: u32) -> Option<Award> {
+        self.try_get_award(index)
+    }
+
+    fn awards_len(&self) -> u32 {
+        self.awards_length()
+    }
+
+    fn stream_coinday(&self) -> (u128, u128) {
+        self.total_coinday()
+    }
+
+    fn stream_reward(&self) -> u128 {
+        self.total_reward()
+    }
+
+    fn reward_balance(&self, user: AccountId) -> u128 {
+        self.reward_of(user)
+    }
+
+    fn set_coinday(&mut self, user: AccountId, info: Coinday) {
+        assert!(self.update_coindays(user, info.amount, info.timestamp, info.last_index).is_ok());
+    }
+
+    fn remove_coinday(&mut self, user: AccountId) {
+        assert!(self.remove_coindays(user).is_ok());
+    }
+
+    fn set_stream_coinday(&mut self, new_value: (u128, u128)) {
+        assert!(self.update_total_coinday(new_value).is_ok());
+    }
+
+    fn set_stream_reward(&mut self, new_value: u128) {
+        assert!(self.update_total_reward(new_value).is_ok());
+    }
+
+    fn set_reward_balance(&mut self, user: AccountId, value: u128) {
+        assert!(self.update_rewards(user, value).is_ok());
+    }
+
+    fn push_award(&mut self, award: Award) {
+        assert!(self.update_awards(award.amount, award.total_coinday, award.timestamp).is_ok());
+    }
+}
 
 #[ink::contract]
 mod reward {
-    // #[cfg(not(feature = "ink-as-dependency"))]
+    pub use coinday::{Coinday, Award, Checkpoint, Scope, Vesting, VestingConfig};
+    use coinday::{Ledger, Elp};
     use ink_storage::collections::HashMap as StorageHashMap;
-    // #[cfg(not(feature = "ink-as-dependency"))]
-    use ink_prelude::vec::Vec;
-    use ink_storage::traits::{SpreadLayout, PackedLayout};
 
     /// The error types
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         OnlyOwnerAccess,
+        OnlyWriterAccess,
+        PeriodCompacted,
+        InvalidVestingConfig,
+        NothingToClaim,
+        /// The user has published periods left to collect.
+        UncollectedPeriods,
+        /// The contract holds less ELP than the claimed reward.
+        InsufficientFunds,
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
 
-    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Coinday {
-        // coinday of user
-        pub amount: u128,
-        // last time for update coinday
-        pub timestamp: u128,
-        // awards' index: record the index of award already got.
-        pub last_index: u32,
+    /// Event emitted when the owner grants or revokes a write scope.
+    #[ink(event)]
+    pub struct WriterUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        scope: Scope,
+        allowed: bool,
     }
 
-    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Award {
-        // current elc increase amount
-        pub amount: u128,
-        // total coinday at current timestamp
-        pub total_coinday: u128,
-        // current timestamp
-        pub timestamp: u128,
+    #[ink(event)]
+    pub struct TotalRewardUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        value: u128,
+    }
+
+    #[ink(event)]
+    pub struct RewardUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+        value: u128,
+    }
+
+    #[ink(event)]
+    pub struct TotalCoindayUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        total_coinday: u128,
+        timestamp: u128,
+    }
+
+    #[ink(event)]
+    pub struct CoindayUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        timestamp: u128,
+        last_index: u32,
+    }
+
+    #[ink(event)]
+    pub struct CoindayRemoved {
+        #[ink(topic)]
+        writer: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AwardsCompacted {
+        #[ink(topic)]
+        writer: AccountId,
+        folded: u32,
+        checkpoint_index: u32,
+    }
+
+    #[ink(event)]
+    pub struct AwardPublished {
+        #[ink(topic)]
+        writer: AccountId,
+        index: u32,
+        amount: u128,
+        total_coinday: u128,
+        timestamp: u128,
+    }
+
+    #[ink(event)]
+    pub struct DailyAwardUpdated {
+        #[ink(topic)]
+        writer: AccountId,
+        amount: u128,
+        timestamp: u128,
+    }
+
+    #[ink(event)]
+    pub struct VestingConfigUpdated {
+        enabled: bool,
+        cliff: u128,
+        duration: u128,
+        penalty: u128,
+    }
+
+    /// Event emitted when ELP is deposited to pay out rewards.
+    #[ink(event)]
+    pub struct Funded {
+        #[ink(topic)]
+        from: AccountId,
+        amount: u128,
+    }
+
+    /// Event emitted when a user claims its credited ELP.
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        vested: bool,
+    }
+
+    #[ink(event)]
+    pub struct VestingWithdrawn {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct VestingExited {
+        #[ink(topic)]
+        user: AccountId,
+        amount: u128,
+        penalty: u128,
+        redistributed: bool,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
     }
 
     /// Defines the storage of your contract.
@@ -47,20 +212,14 @@ mod reward {
     /// to add new static storage fields to your contract.
     #[ink(storage)]
     pub struct Reward {
-        /// Total reward.
-        total_reward: u128,
-        /// Mapping from owner to reward owned.
-        rewards: StorageHashMap<AccountId, u128>,
-        /// Total coinday (total_coinday, last_update_time)
-        total_coinday: (u128, u128),
-        /// Mapping from owner to a tuple (coinday, last_update_time)
-        coindays: StorageHashMap<AccountId, Coinday>,
-        /// award info of elp award each day
-        awards: Vec<Award>,
+        /// rewards, coindays and award info of elp award each day
+        ledger: Ledger,
         /// begin time of distribute block awards(daily award amount, timestamp).
         daily_award: (u128, u128),
         /// begin time of deployment
         deploy_time: u128,
+        /// Mapping from user to the ELP reward already claimed.
+        claimed: StorageHashMap<AccountId, u128>,
         /// The contract owner
         owner: AccountId,
     }
@@ -70,63 +229,59 @@ mod reward {
         #[ink(constructor)]
         pub fn new() -> Self {
             let now_time = Self::env().block_timestamp().into();
-            let awards: Vec<Award> = Vec::new();
             let owner: AccountId = Self::env().caller();
-            let coinday_info = Coinday {
-                amount: 0, 
-                timestamp: now_time, 
-                last_index: 0 
-            };
-            let mut coindays = StorageHashMap::new();
-            coindays.insert(owner, coinday_info);
             Self {
-                total_reward: 0,
-                rewards: StorageHashMap::new(),
-                total_coinday: (0, now_time),
-                coindays,
-                awards,
+                ledger: Ledger::new(now_time),
                 // 首日奖励20000elp
                 daily_award: (20000*1e8 as u128, now_time),
                 deploy_time: now_time,
+                claimed: StorageHashMap::new(),
                 owner,
             }
         }
 
         #[ink(message)]
         pub fn total_reward(&self) -> u128 {
-            self.total_reward
+            self.ledger.total_reward()
         }
 
         #[ink(message)]
         pub fn reward_of(&self, user: AccountId) -> u128 {
-            self.rewards.get(&user).copied().unwrap_or(0)
+            self.ledger.reward_of(&user)
         }
 
         #[ink(message)]
         pub fn total_coinday(&self) -> (u128, u128) {
-            self.total_coinday
+            self.ledger.total_coinday()
         }
 
+        /// Award of period `index`, panics if the period is not published or already folded.
         #[ink(message)]
         pub fn get_award(&self, index: u32) -> Award {
-            self.awards[index as usize].clone()
+            self.ledger.get_award(index).expect("Award period not found")
+        }
+
+        /// `None` if the period is not published or already folded.
+        #[ink(message)]
+        pub fn try_get_award(&self, index: u32) -> Option<Award> {
+            self.ledger.get_award(index)
         }
 
         #[ink(message)]
         pub fn awards_length(&self) -> u32 {
-            self.awards.len() as u32
+            self.ledger.awards_length()
+        }
+
+        /// Fully settled periods folded so far, awards are only available from its index.
+        #[ink(message)]
+        pub fn checkpoint(&self) -> Checkpoint {
+            self.ledger.checkpoint()
         }
 
         #[ink(message)]
         pub fn get_coinday_info(&self, user: AccountId) -> Coinday {
             let now_time = self.env().block_timestamp().into();
-            let coinday_info = Coinday {
-                amount: 0, 
-                timestamp: now_time, 
-                last_index: 0 
-            };
-            let v = self.coindays.get(&user).unwrap_or(&coinday_info);
-            (*v).clone()
+            self.ledger.get_coinday_info(&user, now_time)
         }
 
         #[ink(message)]
@@ -139,24 +294,54 @@ mod reward {
             self.deploy_time
         }
 
+        /// Whether `writer` is allowed to write in `scope`, the owner is allowed in all scopes.
         #[ink(message)]
-        pub fn update_total_reward(&mut self, new_value: u128) -> Result<()> {
+        pub fn is_writer(&self, writer: AccountId, scope: Scope) -> bool {
+            writer == self.owner || self.ledger.has_scope(&writer, scope)
+        }
+
+        /// Allow `writer`(e.g. RELP or Stable contract) to write in `scope`.
+        #[ink(message)]
+        pub fn grant_writer(&mut self, writer: AccountId, scope: Scope) -> Result<()> {
             self.only_owner()?;
-            self.total_reward = new_value;
+            self.ledger.set_scope(writer, scope, true);
+            self.env().emit_event(WriterUpdated { writer, scope, allowed: true });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_writer(&mut self, writer: AccountId, scope: Scope) -> Result<()> {
+            self.only_owner()?;
+            self.ledger.set_scope(writer, scope, false);
+            self.env().emit_event(WriterUpdated { writer, scope, allowed: false });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn update_total_reward(&mut self, new_value: u128) -> Result<()> {
+            let writer = self.only_writer(Scope::AwardPublisher)?;
+            self.ledger.set_total_reward(new_value);
+            self.env().emit_event(TotalRewardUpdated { writer, value: new_value });
             Ok(())
         } 
 
         #[ink(message)]
         pub fn update_rewards(&mut self, user: AccountId, value: u128) -> Result<()> {
-            self.only_owner()?;
-            self.rewards.insert(user, value);
+            let writer = self.only_writer(Scope::RewardCrediter)?;
+            self.ledger.set_reward(user, value);
+            self.env().emit_event(RewardUpdated { writer, user, value });
             Ok(())
         } 
 
         #[ink(message)]
         pub fn update_total_coinday(&mut self, new_value: (u128, u128)) -> Result<()> {
-            self.only_owner()?;
-            self.total_coinday = new_value;
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            self.ledger.set_total_coinday(new_value);
+            self.env().emit_event(TotalCoindayUpdated {
+                writer,
+                total_coinday: new_value.0,
+                timestamp: new_value.1,
+            });
             Ok(())
         } 
 
@@ -168,13 +353,32 @@ mod reward {
             timestamp: u128,
             index: u32
         ) -> Result<()> {
-            self.only_owner()?;
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            if index < self.ledger.checkpoint().index {
+                return Err(Error::PeriodCompacted)
+            }
             let info = Coinday {
                 amount: coinday,
                 timestamp,
                 last_index: index
             };
-            self.coindays.insert(user, info);
+            self.ledger.set_coinday(user, info);
+            self.env().emit_event(CoindayUpdated {
+                writer,
+                user,
+                amount: coinday,
+                timestamp,
+                last_index: index,
+            });
+            Ok(())
+        }
+
+        /// Remove the coinday record of `user` once it holds nothing.
+        #[ink(message)]
+        pub fn remove_coindays(&mut self, user: AccountId) -> Result<()> {
+            let writer = self.only_writer(Scope::CoindayUpdater)?;
+            self.ledger.remove_coinday(&user);
+            self.env().emit_event(CoindayRemoved { writer, user });
             Ok(())
         }
 
@@ -185,28 +389,174 @@ mod reward {
             total_coinday: u128, 
             timestamp: u128
         ) -> Result<()> {
-            self.only_owner()?;
+            let writer = self.only_writer(Scope::AwardPublisher)?;
             let new_award = Award {
                 amount,
                 total_coinday,
                 timestamp,
             };
-            self.awards.push(new_award);
+            self.ledger.push_award(new_award);
+            self.env().emit_event(AwardPublished {
+                writer,
+                index: self.ledger.awards_length() - 1,
+                amount,
+                total_coinday,
+                timestamp,
+            });
             Ok(())
         }
         
         /// update amount of award for each day(amount, timestamp).
         #[ink(message)]
         pub fn update_daily_award(&mut self, new_amount: (u128, u128)) -> Result<()> {
-            self.only_owner()?;
+            let writer = self.only_writer(Scope::AwardPublisher)?;
             self.daily_award = new_amount;
+            self.env().emit_event(DailyAwardUpdated {
+                writer,
+                amount: new_amount.0,
+                timestamp: new_amount.1,
+            });
             Ok(())
         }
 
+        /// Fold at most `max_periods` old periods which every holder has collected into
+        /// the checkpoint. A holder left behind blocks the folding until it is settled, e.g. by
+        /// the keepers liquidating rewards in RELP. Only callable by award publishers.
+        /// Returns the number of folded periods.
+        #[ink(message)]
+        pub fn compact_awards(&mut self, max_periods: u32) -> Result<u32> {
+            let writer = self.only_writer(Scope::AwardPublisher)?;
+            let folded = self.ledger.compact(max_periods);
+            if folded > 0 {
+                self.env().emit_event(AwardsCompacted {
+                    writer,
+                    folded,
+                    checkpoint_index: self.ledger.checkpoint().index,
+                });
+            }
+            Ok(folded)
+        }
+
+        #[ink(message)]
+        pub fn vesting_config(&self) -> VestingConfig {
+            self.ledger.vesting_config()
+        }
+
+        /// Set the vesting schedule of claimed rewards, `enabled: false` pays them out directly.
+        #[ink(message)]
+        pub fn set_vesting_config(&mut self, config: VestingConfig) -> Result<()> {
+            self.only_owner()?;
+            if config.enabled && !config.is_valid() {
+                return Err(Error::InvalidVestingConfig)
+            }
+            self.ledger.set_vesting_config(config.clone());
+            self.env().emit_event(VestingConfigUpdated {
+                enabled: config.enabled,
+                cliff: config.cliff,
+                duration: config.duration,
+                penalty: config.penalty,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn vesting_of(&self, user: AccountId) -> Option<Vesting> {
+            self.ledger.vesting_of(&user)
+        }
+
+        /// Withdraw the vested part of the caller's position.
+        #[ink(message)]
+        pub fn withdraw_vested(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let now_time = self.env().block_timestamp().into();
+            let amount = self.ledger.withdraw_vested(&caller, now_time);
+            if amount == 0 {
+                return Err(Error::NothingToClaim)
+            }
+            self.pay(caller, amount);
+            self.env().emit_event(VestingWithdrawn { user: caller, amount });
+            Ok(amount)
+        }
+
+        /// Close the position of `user` before its end, called by RELP which brings the coinday
+        /// of `user` and the total coinday up to now first. The unvested part is paid out minus
+        /// the exit penalty, which is published as a new period to the other holders split by
+        /// `total_coinday`, the total coinday now without the coinday of `user`.
+        #[ink(message)]
+        pub fn exit_vesting(&mut self, user: AccountId, total_coinday: u128) -> Result<Balance> {
+            self.only_writer(Scope::RewardCrediter)?;
+            let now_time = self.env().block_timestamp().into();
+            if self.ledger.get_coinday_info(&user, now_time).last_index < self.ledger.awards_length() {
+                return Err(Error::UncollectedPeriods)
+            }
+            let (mut amount, penalty) = self.ledger.exit_vesting(&user, now_time);
+            if amount + penalty == 0 {
+                return Err(Error::NothingToClaim)
+            }
+            // the penalty ELP stays in this contract to pay the new period.
+            let redistributed = penalty > 0
+                && self.ledger.redistribute::<Elp>(&user, penalty, total_coinday, now_time);
+            if !redistributed {
+                // nobody to redistribute to, no penalty is taken.
+                amount += penalty;
+            }
+            self.pay(user, amount);
+            self.env().emit_event(VestingExited { user, amount, penalty, redistributed });
+            Ok(amount)
+        }
+
+        /// Credited ELP reward of `user` not claimed yet.
+        #[ink(message)]
+        pub fn claimable_of(&self, user: AccountId) -> u128 {
+            let claimed = self.claimed.get(&user).copied().unwrap_or(0);
+            self.ledger.reward_of(&user).saturating_sub(claimed)
+        }
+
+        /// Deposit ELP to pay out the credited rewards, e.g. from Stable or the treasury.
+        #[ink(message, payable)]
+        pub fn fund(&mut self) {
+            self.env().emit_event(Funded {
+                from: self.env().caller(),
+                amount: self.env().transferred_balance(),
+            });
+        }
+
+        /// Claim the credited ELP reward of the caller. It is paid out directly, or locked into
+        /// the caller's vesting position when vesting is enabled. The contract must hold the
+        /// whole claimed amount, deposited with `fund`.
+        /// Returns the amount paid out now.
+        #[ink(message)]
+        pub fn claim(&mut self) -> Result<Balance> {
+            let caller = self.env().caller();
+            let amount = self.claimable_of(caller);
+            if amount == 0 {
+                return Err(Error::NothingToClaim)
+            }
+            if self.env().balance() < amount {
+                return Err(Error::InsufficientFunds)
+            }
+            self.claimed.insert(caller, self.ledger.reward_of(&caller));
+
+            let config = self.ledger.vesting_config();
+            let paid = if config.enabled {
+                let now_time = self.env().block_timestamp().into();
+                self.ledger.vest(caller, amount, now_time)
+            } else {
+                amount
+            };
+            if paid > 0 {
+                self.pay(caller, paid);
+            }
+            self.env().emit_event(Claimed { user: caller, amount, vested: config.enabled });
+            Ok(paid)
+        }
+
         #[ink(message)]
         pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
             self.only_owner()?;
+            let previous_owner = self.owner;
             self.owner = new_owner;
+            self.env().emit_event(OwnershipTransferred { previous_owner, new_owner });
             Ok(())
         }
 
@@ -222,6 +572,22 @@ mod reward {
             }
             Ok(())
         }
+
+        /// Panics on failure, so the ledger writes made before the payment are reverted.
+        fn pay(&self, user: AccountId, amount: Balance) {
+            if self.env().transfer(user, amount).is_err() {
+                panic!("ELP transfer failed")
+            }
+        }
+
+        /// Returns the caller if it is allowed to write in `scope`.
+        fn only_writer(&self, scope: Scope) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_writer(caller, scope) {
+                return Err(Error::OnlyWriterAccess)
+            }
+            Ok(caller)
+        }
     }
 
     /// Unit tests.
@@ -239,6 +605,23 @@ mod reward {
             test::default_accounts().expect("Cannot get accounts")
         }
 
+        fn set_caller(caller: AccountId) {
+            let callee = account_id::<DefaultEnvironment>();
+            let data = test::CallData::new(call::Selector::new([0x00; 4]));
+            test::push_execution_context::<DefaultEnvironment>(
+                caller,
+                callee,
+                100000,
+                100000,
+                data,
+            );
+        }
+
+        fn fund_contract(amount: Balance) {
+            test::set_account_balance::<DefaultEnvironment>(account_id::<DefaultEnvironment>(), amount)
+                .expect("Cannot set balance");
+        }
+
         #[ink::test]
         fn new_works() {
             let reward = Reward::new(); 
@@ -262,7 +645,7 @@ mod reward {
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(reward.update_total_reward(20), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_total_reward(20), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
@@ -279,7 +662,7 @@ mod reward {
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(reward.update_rewards(accounts.alice, 20), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_rewards(accounts.alice, 20), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
@@ -295,7 +678,7 @@ mod reward {
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
             // bob is caller, alice is owner
-            assert_eq!(reward.update_total_coinday((10, 20)), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_total_coinday((10, 20)), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
@@ -312,7 +695,7 @@ mod reward {
             let mut reward = Reward::new();
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
-            assert_eq!(reward.update_coindays(accounts.bob, 66, 10, 0), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_coindays(accounts.bob, 66, 10, 0), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
@@ -328,7 +711,7 @@ mod reward {
             let mut reward = Reward::new();
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
-            assert_eq!(reward.update_awards(10, 33, 166600), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_awards(10, 33, 166600), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
@@ -343,7 +726,238 @@ mod reward {
             let mut reward = Reward::new();
             let accounts = default_accounts();
             assert!(reward.transfer_ownership(accounts.bob).is_ok());
-            assert_eq!(reward.update_daily_award((200, 166666)), Err(Error::OnlyOwnerAccess));
+            assert_eq!(reward.update_daily_award((200, 166666)), Err(Error::OnlyWriterAccess));
+        }
+
+        #[ink::test]
+        fn compact_awards_works() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(reward.update_awards(10, 100, 1).is_ok());
+            assert!(reward.update_awards(20, 200, 2).is_ok());
+            assert!(reward.update_awards(30, 300, 3).is_ok());
+            // alice still has to collect period 1
+            assert!(reward.update_coindays(accounts.alice, 66, 1, 1).is_ok());
+            assert_eq!(reward.compact_awards(10), Ok(1));
+            let checkpoint = Checkpoint {index: 1, timestamp: 1};
+            assert_eq!(reward.checkpoint(), checkpoint);
+            assert_eq!(reward.get_award(1), Award {amount: 20, total_coinday: 200, timestamp: 2});
+            assert_eq!(reward.try_get_award(0), None);
+
+            // alice collected all periods, the rest can be folded.
+            assert!(reward.update_coindays(accounts.alice, 66, 3, 3).is_ok());
+            assert_eq!(reward.compact_awards(10), Ok(2));
+            let checkpoint = Checkpoint {index: 3, timestamp: 3};
+            assert_eq!(reward.checkpoint(), checkpoint);
+            assert_eq!(reward.awards_length(), 3);
+            assert_eq!(reward.compact_awards(10), Ok(0));
+        }
+
+        #[ink::test]
+        fn compact_awards_respects_max_periods() {
+            let mut reward = Reward::new();
+            assert!(reward.update_awards(10, 100, 1).is_ok());
+            assert!(reward.update_awards(20, 200, 2).is_ok());
+            assert_eq!(reward.compact_awards(1), Ok(1));
+            assert_eq!(reward.checkpoint().index, 1);
+            assert_eq!(reward.compact_awards(1), Ok(1));
+            assert_eq!(reward.checkpoint().index, 2);
+        }
+
+        #[ink::test]
+        fn compact_awards_failed_when_not_publisher() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(reward.update_awards(10, 100, 1).is_ok());
+            set_caller(accounts.bob);
+            assert_eq!(reward.compact_awards(10), Err(Error::OnlyWriterAccess));
+            assert_eq!(reward.checkpoint().index, 0);
+        }
+
+        #[ink::test]
+        fn update_coindays_failed_when_period_compacted() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(reward.update_awards(10, 100, 1).is_ok());
+            assert_eq!(reward.compact_awards(10), Ok(1));
+            assert_eq!(reward.update_coindays(accounts.alice, 66, 1, 0), Err(Error::PeriodCompacted));
+            assert!(reward.update_coindays(accounts.alice, 66, 1, 1).is_ok());
+        }
+
+        #[ink::test]
+        fn remove_coindays_works() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(reward.update_awards(10, 100, 1).is_ok());
+            assert!(reward.update_coindays(accounts.bob, 66, 1, 0).is_ok());
+            assert_eq!(reward.compact_awards(10), Ok(0));
+            assert!(reward.remove_coindays(accounts.bob).is_ok());
+            assert_eq!(reward.get_coinday_info(accounts.bob).last_index, 1);
+            assert_eq!(reward.compact_awards(10), Ok(1));
+        }
+
+        #[ink::test]
+        fn grant_writer_works() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(!reward.is_writer(accounts.bob, Scope::CoindayUpdater));
+            assert!(reward.grant_writer(accounts.bob, Scope::CoindayUpdater).is_ok());
+            assert!(reward.is_writer(accounts.bob, Scope::CoindayUpdater));
+            assert!(!reward.is_writer(accounts.bob, Scope::RewardCrediter));
+
+            // bob writes in its own scope only.
+            set_caller(accounts.bob);
+            assert!(reward.update_coindays(accounts.charlie, 66, 1000, 3).is_ok());
+            assert!(reward.update_total_coinday((10, 20)).is_ok());
+            assert_eq!(reward.update_rewards(accounts.charlie, 20), Err(Error::OnlyWriterAccess));
+            assert_eq!(reward.update_awards(10, 33, 66), Err(Error::OnlyWriterAccess));
+        }
+
+        #[ink::test]
+        fn grant_writer_failed() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            set_caller(accounts.bob);
+            assert_eq!(reward.grant_writer(accounts.bob, Scope::AwardPublisher), Err(Error::OnlyOwnerAccess));
+        }
+
+        #[ink::test]
+        fn revoke_writer_works() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert!(reward.grant_writer(accounts.bob, Scope::AwardPublisher).is_ok());
+            assert!(reward.grant_writer(accounts.bob, Scope::RewardCrediter).is_ok());
+            assert!(reward.revoke_writer(accounts.bob, Scope::AwardPublisher).is_ok());
+            assert!(!reward.is_writer(accounts.bob, Scope::AwardPublisher));
+            assert!(reward.is_writer(accounts.bob, Scope::RewardCrediter));
+
+            set_caller(accounts.bob);
+            assert_eq!(reward.update_awards(10, 33, 66), Err(Error::OnlyWriterAccess));
+            assert!(reward.update_rewards(accounts.charlie, 20).is_ok());
+        }
+
+        fn vesting_config() -> VestingConfig {
+            VestingConfig { enabled: true, cliff: 10, duration: 100, penalty: 5000 }
+        }
+
+        #[ink::test]
+        fn set_vesting_config_works() {
+            let mut reward = Reward::new();
+            assert!(!reward.vesting_config().enabled);
+            assert!(reward.set_vesting_config(vesting_config()).is_ok());
+            assert_eq!(reward.vesting_config(), vesting_config());
+        }
+
+        #[ink::test]
+        fn set_vesting_config_failed() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            let invalid = VestingConfig { enabled: true, cliff: 200, duration: 100, penalty: 5000 };
+            assert_eq!(reward.set_vesting_config(invalid), Err(Error::InvalidVestingConfig));
+
+            set_caller(accounts.bob);
+            assert_eq!(reward.set_vesting_config(vesting_config()), Err(Error::OnlyOwnerAccess));
+        }
+
+        #[ink::test]
+        fn claim_into_vesting_works() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(1000);
+            assert!(reward.set_vesting_config(vesting_config()).is_ok());
+            assert!(reward.update_rewards(accounts.alice, 300).is_ok());
+            assert_eq!(reward.claimable_of(accounts.alice), 300);
+
+            // nothing is paid before the cliff.
+            assert_eq!(reward.claim(), Ok(0));
+            assert_eq!(reward.claimable_of(accounts.alice), 0);
+            let vesting = reward.vesting_of(accounts.alice).unwrap();
+            assert_eq!(vesting.amount, 300);
+            assert_eq!(vesting.withdrawn, 0);
+            assert_eq!(reward.withdraw_vested(), Err(Error::NothingToClaim));
+        }
+
+        #[ink::test]
+        fn claimable_does_not_underflow() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(1000);
+            assert!(reward.set_vesting_config(vesting_config()).is_ok());
+            assert!(reward.update_rewards(accounts.alice, 300).is_ok());
+            assert_eq!(reward.claim(), Ok(0));
+            // the writer lowers the credited reward below what was claimed.
+            assert!(reward.update_rewards(accounts.alice, 100).is_ok());
+            assert_eq!(reward.claimable_of(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn claim_pays_out_funded_elp() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(1000);
+            assert!(reward.update_rewards(accounts.bob, 300).is_ok());
+            let before = test::get_account_balance::<DefaultEnvironment>(accounts.bob).expect("Cannot get balance");
+
+            set_caller(accounts.bob);
+            assert_eq!(reward.claim(), Ok(300));
+            let after = test::get_account_balance::<DefaultEnvironment>(accounts.bob).expect("Cannot get balance");
+            assert_eq!(after - before, 300);
+            assert_eq!(reward.claimable_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn claim_failed_when_unfunded() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(100);
+            assert!(reward.update_rewards(accounts.alice, 300).is_ok());
+            assert_eq!(reward.claim(), Err(Error::InsufficientFunds));
+            assert_eq!(reward.claimable_of(accounts.alice), 300);
+        }
+
+        #[ink::test]
+        fn claim_failed_when_nothing_credited() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            assert_eq!(reward.claim(), Err(Error::NothingToClaim));
+            assert_eq!(reward.exit_vesting(accounts.alice, 100), Err(Error::NothingToClaim));
+        }
+
+        #[ink::test]
+        fn exit_vesting_redistributes_penalty_to_others() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(1000);
+            let now_time: u128 = ink_env::block_timestamp::<DefaultEnvironment>().into();
+            assert!(reward.set_vesting_config(vesting_config()).is_ok());
+            assert!(reward.update_rewards(accounts.alice, 400).is_ok());
+            assert_eq!(reward.claim(), Ok(0));
+            assert!(reward.update_coindays(accounts.alice, 100, now_time, 0).is_ok());
+            assert!(reward.update_coindays(accounts.bob, 300, now_time, 0).is_ok());
+
+            // nothing is vested yet, half of the locked part is taken.
+            assert_eq!(reward.exit_vesting(accounts.alice, 300), Ok(200));
+            assert_eq!(reward.get_award(0), Award { amount: 200, total_coinday: 300, timestamp: now_time });
+            // alice does not collect its own penalty.
+            assert_eq!(reward.get_coinday_info(accounts.alice).last_index, 1);
+            assert_eq!(reward.get_coinday_info(accounts.bob).last_index, 0);
+            assert_eq!(reward.vesting_of(accounts.alice), None);
+        }
+
+        #[ink::test]
+        fn exit_vesting_failed_with_uncollected_periods() {
+            let mut reward = Reward::new();
+            let accounts = default_accounts();
+            fund_contract(1000);
+            assert!(reward.set_vesting_config(vesting_config()).is_ok());
+            assert!(reward.update_rewards(accounts.alice, 400).is_ok());
+            assert_eq!(reward.claim(), Ok(0));
+            assert!(reward.update_coindays(accounts.alice, 100, 0, 0).is_ok());
+            assert!(reward.update_awards(10, 100, 0).is_ok());
+            assert_eq!(reward.exit_vesting(accounts.alice, 100), Err(Error::UncollectedPeriods));
+
+            set_caller(accounts.bob);
+            assert_eq!(reward.exit_vesting(accounts.alice, 100), Err(Error::OnlyWriterAccess));
         }
 
         #[ink::test]
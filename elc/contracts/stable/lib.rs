@@ -2,7 +2,7 @@
 #![allow(non_snake_case)]
 #![allow(unused_mut)]
 
-pub use self::stable::Stable;
+pub use self::stable::{Stable, StableRef};
 use ink_lang as ink;
 #[ink::contract]
 mod stable {
@@ -11,12 +11,566 @@ mod stable {
     use oracle::OracleRef;
     use govern::GovernRef;
     use additional::AdditionalRef;
-    use exchange2::PatraExchange2Ref;
+    use coinday::{add, mul, mul_div, sub, Distribution, Elc, MathError};
+    use swap::{input_price, min_out, share_of, SwapAdapter};
 
     // #[cfg(not(feature = "ink-as-dependency"))]
-    use ink_env::call::FromAccountId;
+    use ink_env::call::{build_call, utils::ReturnType, ExecutionInput, FromAccountId, Selector};
+    use ink_env::DefaultEnvironment;
     // #[cfg(not(feature = "ink-as-dependency"))]
-    use ink_storage::lazy::Lazy;
+    use ink_storage::{
+        collections::HashMap as StorageHashMap,
+        lazy::Lazy,
+        traits::{PackedLayout, SpreadLayout},
+    };
+    use ink_prelude::{string::String, vec::Vec};
+
+    /// The Stable error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        OnlyGovernAccess,
+        InvalidBountyRate,
+        InvalidParameters,
+        OnlyGuardianAccess,
+        /// Stable is shut down, only settlement redemption works.
+        Shutdown,
+        /// Stable is not shut down yet.
+        NotShutdown,
+        /// Settlement pot is paid out.
+        SettlementExhausted,
+        InvalidAmount,
+        /// ELC redeemed in this window reaches `redeem_cap`.
+        RedeemLimitExceeded,
+        /// Token is not a whitelisted collateral.
+        UnknownCollateral,
+        /// Collateral in reserves would exceed its `debt_ceiling`.
+        DebtCeilingExceeded,
+        /// A recapitalization auction is already running.
+        AuctionActive,
+        NoAuction,
+        /// Auction duration has passed, it can only be settled.
+        AuctionEnded,
+        /// Auction duration has not passed and the target is not raised.
+        AuctionNotEnded,
+        /// Liability ratio or risk reserve does not call for recapitalization.
+        RecapNotNeeded,
+        /// Oracle or exchange price is zero.
+        PriceUnavailable,
+        /// Liability ratio does not allow the operation.
+        LiabilityRatioOutOfRange,
+        /// ELC price is within the band where no adjustment is needed.
+        ElcPriceInRange,
+        AdjustGapNotElapsed,
+        /// ELP reserve does not cover the ELC liabilities.
+        InsufficientReserve,
+        MintFailed,
+        BurnFailed,
+        TransferFailed,
+        SwapFailed,
+        /// Output is below the minimum or input above the maximum given by the caller.
+        SlippageExceeded,
+        DeadlineExpired,
+        /// Exchange pool has no liquidity to size the trade.
+        InsufficientLiquidity,
+        /// ELP in protocol-owned liquidity would exceed `pol_share` of risk reserve.
+        PolCapExceeded,
+        /// Shares to withdraw exceed the protocol-owned liquidity.
+        InsufficientShares,
+        Overflow,
+        Underflow,
+    }
+
+    impl From<MathError> for Error {
+        fn from(error: MathError) -> Self {
+            match error {
+                MathError::Overflow => Error::Overflow,
+                MathError::Underflow => Error::Underflow,
+            }
+        }
+    }
+
+    /// Max price impact of one contraction, base 10000.
+    const MAX_PRICE_IMPACT: u128 = 5000;
+    /// Max keeper bounty rate of the ELP traded by one adjustment, base 10000.
+    const MAX_KEEPER_BOUNTY_RATE: u128 = 100;
+    /// Bounds of `Parameters`.
+    const MAX_RESERVE_DRAW_CAP: u128 = 10;
+    const MIN_ADJUST_GAP: u128 = 3600 * 1000;
+    const MAX_ADJUST_GAP: u128 = 7 * 24 * 3600 * 1000;
+    const MAX_REDEEM_FEE: u128 = 1000;
+    const MAX_REDEEM_CAP: u128 = 1000;
+    const MAX_LIQUIDITY_FEE: u128 = 500;
+    const AUCTION_PRICE_BASE: u128 = 100000;
+    /// Output a swap may fall short of its quote by, base 10000.
+    const SWAP_TOLERANCE: u128 = 100;
+
+    /// The Stable result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Fee charged on `add_liquidity` or `remove_liquidity`, base 10000.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub enum FeeSchedule {
+        /// The same rate at any liability ratio.
+        Flat(u128),
+        /// Moves linearly from `low` at liability ratio 0 to `high` at 100.
+        Dynamic { low: u128, high: u128 },
+    }
+
+    impl FeeSchedule {
+        /// Fee rate at liability ratio `lr`.
+        pub fn rate(&self, lr: u128) -> u128 {
+            match *self {
+                FeeSchedule::Flat(rate) => rate,
+                FeeSchedule::Dynamic { low, high } => {
+                    let lr = lr.min(100);
+                    if high >= low {
+                        low + (high - low) * lr / 100
+                    } else {
+                        low - (low - high) * lr / 100
+                    }
+                }
+            }
+        }
+
+        pub fn is_valid(&self) -> bool {
+            match *self {
+                FeeSchedule::Flat(rate) => rate <= MAX_LIQUIDITY_FEE,
+                FeeSchedule::Dynamic { low, high } => low <= MAX_LIQUIDITY_FEE && high <= MAX_LIQUIDITY_FEE,
+            }
+        }
+    }
+
+    /// Policy of `Stable`, set by govern contract. Ratios are base 100.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Parameters {
+        /// Below this liability ratio deposits are split into rELP and ELC.
+        pub issue_ratio: u128,
+        /// Below this liability ratio ELC can expand.
+        pub expand_ratio: u128,
+        /// Above this liability ratio liquidity can be removed.
+        pub remove_ratio: u128,
+        /// Share of issued ELC published to rELP holders, the rest is sold for ELP.
+        pub reward_share: u128,
+        /// ELC contracts when its price is below this share of ELCaim.
+        pub contract_band: u128,
+        /// Max share of reserve sold by one contraction when risk reserve is empty.
+        pub reserve_draw_cap: u128,
+        /// Min time between two expansions or contractions, in milliseconds.
+        pub adjust_gap: u128,
+        /// Below this liability ratio ELC can be redeemed at ELCaim.
+        pub redeem_ratio: u128,
+        /// Fee of ELC redemption, base 10000.
+        pub redeem_fee: u128,
+        /// Max ELC redeemed per `adjust_gap`, share of ELC supply, base 10000.
+        pub redeem_cap: u128,
+        /// Fee of `add_liquidity`, paid in ELP to risk reserve.
+        pub mint_fee: FeeSchedule,
+        /// Fee of `remove_liquidity`, paid in ELP to risk reserve.
+        pub remove_fee: FeeSchedule,
+        /// Max share of risk reserve, including what is deployed, put in the exchange pool
+        /// as protocol-owned liquidity.
+        pub pol_share: u128,
+    }
+
+    impl Default for Parameters {
+        fn default() -> Self {
+            Self {
+                issue_ratio: 30,
+                expand_ratio: 70,
+                remove_ratio: 90,
+                reward_share: 95,
+                contract_band: 98,
+                reserve_draw_cap: 2,
+                adjust_gap: 3600 * 24 * 1000, // one day
+                redeem_ratio: 70,
+                redeem_fee: 50,
+                redeem_cap: 100,
+                mint_fee: FeeSchedule::Flat(0),
+                remove_fee: FeeSchedule::Flat(0),
+                pol_share: 50,
+            }
+        }
+    }
+
+    impl Parameters {
+        pub fn is_valid(&self) -> bool {
+            0 < self.issue_ratio
+                && self.issue_ratio < self.expand_ratio
+                && self.expand_ratio < self.remove_ratio
+                && self.remove_ratio < 100
+                && (50..100).contains(&self.reward_share)
+                && (90..100).contains(&self.contract_band)
+                && (1..=MAX_RESERVE_DRAW_CAP).contains(&self.reserve_draw_cap)
+                && (MIN_ADJUST_GAP..=MAX_ADJUST_GAP).contains(&self.adjust_gap)
+                && (1..100).contains(&self.redeem_ratio)
+                && self.redeem_fee <= MAX_REDEEM_FEE
+                && (1..=MAX_REDEEM_CAP).contains(&self.redeem_cap)
+                && self.mint_fee.is_valid()
+                && self.remove_fee.is_valid()
+                && self.pol_share <= 100
+        }
+    }
+
+    /// Error of a cross-contract call: the call itself failed, or the callee returned an error.
+    #[derive(PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Debug))]
+    pub enum CallError<E> {
+        Env(ink_env::Error),
+        Callee(E),
+    }
+
+    /// Errors of the PSP22 standard.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
+    }
+
+    /// Selector of `PSP22::transfer`.
+    const PSP22_TRANSFER: [u8; 4] = [0xdb, 0x20, 0xf9, 0xf5];
+    /// Selector of `PSP22::transfer_from`.
+    const PSP22_TRANSFER_FROM: [u8; 4] = [0x54, 0xb3, 0xc7, 0x6e];
+
+    /// Handle of a PSP22 token, calling the standard messages by selector.
+    pub struct PSP22Ref {
+        token: AccountId,
+    }
+
+    impl PSP22Ref {
+        pub fn new(token: AccountId) -> Self {
+            Self { token }
+        }
+
+        /// `PSP22::transfer` of `value` to `to`.
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> core::result::Result<(), CallError<PSP22Error>> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER))
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(data))
+                .returns::<ReturnType<core::result::Result<(), PSP22Error>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+
+        /// `PSP22::transfer_from` of `value` from `from` to `to`, `from` must approve the caller.
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> core::result::Result<(), CallError<PSP22Error>> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_FROM))
+                        .push_arg(from)
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(data))
+                .returns::<ReturnType<core::result::Result<(), PSP22Error>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+    }
+
+    /// Errors of the swap adapter messages, in the order of the `exchange` error enum.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SwapError {
+        InvalidAmount,
+        InsufficientLiquidity,
+        TransferFailed,
+        InsufficientShares,
+        SlippageExceeded,
+    }
+
+    impl From<SwapError> for Error {
+        fn from(error: SwapError) -> Self {
+            match error {
+                SwapError::SlippageExceeded => Error::SlippageExceeded,
+                _ => Error::SwapFailed,
+            }
+        }
+    }
+
+    impl From<CallError<SwapError>> for Error {
+        fn from(error: CallError<SwapError>) -> Self {
+            match error {
+                CallError::Env(_) => Error::SwapFailed,
+                CallError::Callee(error) => error.into(),
+            }
+        }
+    }
+
+    /// Handle of a swap adapter, calling the `swap` adapter messages by selector.
+    pub struct SwapRef {
+        exchange: AccountId,
+    }
+
+    impl SwapRef {
+        pub fn new(exchange: AccountId) -> Self {
+            Self { exchange }
+        }
+
+        /// Read-only adapter message without arguments, panics if the call fails.
+        fn query<R: scale::Decode>(&self, selector: [u8; 4]) -> R {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(selector)))
+                .returns::<ReturnType<R>>()
+                .fire()
+                .expect("Exchange query failed")
+        }
+
+        /// Quote of selling `amount`, panics if the call fails.
+        fn quote(&self, selector: [u8; 4], amount: Balance) -> Balance {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(selector)).push_arg(amount))
+                .returns::<ReturnType<Balance>>()
+                .fire()
+                .expect("Exchange query failed")
+        }
+    }
+
+    impl SwapAdapter for SwapRef {
+        type Error = CallError<SwapError>;
+
+        fn quote_elc_to_elp(&self, elc_in: Balance) -> Balance {
+            self.quote(swap::QUOTE_ELC_TO_ELP, elc_in)
+        }
+
+        fn quote_elp_to_elc(&self, elp_in: Balance) -> Balance {
+            self.quote(swap::QUOTE_ELP_TO_ELC, elp_in)
+        }
+
+        fn swap_elc_to_elp(&mut self, elc_in: Balance, min_out: Balance) -> core::result::Result<Balance, Self::Error> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(swap::SWAP_ELC_TO_ELP))
+                        .push_arg(elc_in)
+                        .push_arg(min_out))
+                .returns::<ReturnType<core::result::Result<Balance, SwapError>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+
+        fn swap_elp_to_elc(&mut self, elp_in: Balance, min_out: Balance) -> core::result::Result<Balance, Self::Error> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .transferred_value(elp_in)
+                .exec_input(ExecutionInput::new(Selector::new(swap::SWAP_ELP_TO_ELC)).push_arg(min_out))
+                .returns::<ReturnType<core::result::Result<Balance, SwapError>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+
+        fn reserves(&self) -> (Balance, Balance) {
+            self.query(swap::RESERVES)
+        }
+
+        fn add_liquidity(&mut self, elp_amount: Balance, elc_amount: Balance) -> core::result::Result<Balance, Self::Error> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .transferred_value(elp_amount)
+                .exec_input(ExecutionInput::new(Selector::new(swap::ADD_LIQUIDITY)).push_arg(elc_amount))
+                .returns::<ReturnType<core::result::Result<Balance, SwapError>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+
+        fn remove_liquidity(&mut self, shares: Balance) -> core::result::Result<(Balance, Balance), Self::Error> {
+            build_call::<DefaultEnvironment>()
+                .callee(self.exchange)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(swap::REMOVE_LIQUIDITY)).push_arg(shares))
+                .returns::<ReturnType<core::result::Result<(Balance, Balance), SwapError>>>()
+                .fire()
+                .map_err(CallError::Env)?
+                .map_err(CallError::Callee)
+        }
+
+        fn total_shares(&self) -> Balance {
+            self.query(swap::TOTAL_SHARES)
+        }
+    }
+
+    /// A whitelisted PSP22 collateral, pulled with the standard `transfer_from` message.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Collateral {
+        /// Oracle contract feeding `asset_price` of the token.
+        pub oracle: AccountId,
+        /// Share of the oracle value not counted in reserves, base 100.
+        pub haircut: u128,
+        /// Max amount of the token taken into reserves, 0 stops new deposits.
+        pub debt_ceiling: Balance,
+        /// Amount in reserves.
+        pub reserve: Balance,
+        /// Mint fees paid in the token, part of risk reserve.
+        pub risk_reserve: Balance,
+    }
+
+    /// Dutch auction selling newly minted rELP for ELP into reserve. The price falls linearly
+    /// from `start_price` to `floor_price` over `duration`, prices are ELP per rELP with
+    /// decimals 100000.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Auction {
+        pub start_time: u128,
+        pub duration: u128,
+        pub start_price: u128,
+        pub floor_price: u128,
+        /// ELP to raise, the auction settles once reached.
+        pub target: Balance,
+        pub raised: Balance,
+        pub relp_sold: Balance,
+    }
+
+    /// Final settlement after emergency shutdown. ELP reserves are split into a pot for
+    /// ELC holders, worth the ELC supply at the frozen prices, and the rest for rELP holders.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Settlement {
+        pub time: u128,
+        /// Oracle prices frozen at shutdown.
+        pub elc_price: u128,
+        pub elp_price: u128,
+        /// Supplies at shutdown, the redemptions are pro rata to them.
+        pub elc_supply: Balance,
+        pub relp_supply: Balance,
+        /// ELP owed to ELC holders, they are paid first.
+        pub elc_pot: Balance,
+        /// ELP left for rELP holders.
+        pub relp_pot: Balance,
+        pub elc_paid: Balance,
+        pub relp_paid: Balance,
+        /// Pool shares of protocol-owned liquidity which could not be withdrawn at shutdown,
+        /// still held by Stable and left out of the pots.
+        pub pol_shares: Balance,
+    }
+
+    impl Settlement {
+        /// Pay `elc_amount` ELC out of the ELC pot. Returns the ELP owed.
+        pub fn redeem_elc(&mut self, elc_amount: Balance) -> Result<Balance> {
+            take_pro_rata(elc_amount, self.elc_supply, self.elc_pot, &mut self.elc_paid)
+        }
+
+        /// Pay `relp_amount` rELP out of the rELP pot. Returns the ELP owed.
+        pub fn redeem_relp(&mut self, relp_amount: Balance) -> Result<Balance> {
+            take_pro_rata(relp_amount, self.relp_supply, self.relp_pot, &mut self.relp_paid)
+        }
+    }
+
+    /// Collateral reserves split at shutdown in the same proportion as the ELP reserves.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct CollateralPot {
+        /// Tokens owed to ELC holders.
+        pub elc_pot: Balance,
+        /// Tokens left for rELP holders.
+        pub relp_pot: Balance,
+        pub elc_paid: Balance,
+        pub relp_paid: Balance,
+    }
+
+    /// Changes of the reserve buckets, negative when consumed.
+    #[derive(Clone, Debug, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ReserveChanges {
+        /// ELP `reserve`
+        pub reserve: i128,
+        /// ELP `risk_reserve`
+        pub risk_reserve: i128,
+        /// `elc_reserve_source`
+        pub elc_reserve: i128,
+        /// `elc_risk_reserve_source`
+        pub elc_risk_reserve: i128,
+    }
+
+    /// What `expand_elc` or `contract_elc` would do now.
+    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct AdjustPreview {
+        /// Liability ratio allows the adjustment, contraction has no such limit.
+        pub ratio_ok: bool,
+        /// ELC price is out of the band on the adjusted side.
+        pub price_ok: bool,
+        /// `adjust_gap` elapsed since the last adjustment.
+        pub gap_ok: bool,
+        /// ELC sold (expand) or bought (contract).
+        pub elc_amount: Balance,
+        /// ELP bought (expand) or sold (contract).
+        pub elp_amount: Balance,
+        /// Newly issued ELC, 95% of it goes to rELP holders.
+        pub elc_issued: Balance,
+        /// ELP paid to the caller from risk reserve.
+        pub keeper_bounty: Balance,
+        pub changes: ReserveChanges,
+        /// Liability ratio afterwards.
+        pub liability_ratio: u128,
+    }
+
+    /// What `remove_liquidity` would do now.
+    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RemovePreview {
+        /// Liability ratio allows removing liquidity.
+        pub ratio_ok: bool,
+        /// ELC burned.
+        pub elc_in: Balance,
+        /// ELP redeemed, after the fee.
+        pub elp_out: Balance,
+        /// ELP fee paid to risk reserve.
+        pub fee: Balance,
+        pub changes: ReserveChanges,
+        /// Liability ratio afterwards.
+        pub liability_ratio: u128,
+    }
+
+    /// Protocol-owned liquidity in the exchange pool.
+    #[derive(Clone, Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct PolPosition {
+        /// Pool shares held by `Stable`.
+        pub shares: Balance,
+        /// ELP taken from risk reserve and ELC issued for the shares still held.
+        pub elp_deployed: Balance,
+        pub elc_deployed: Balance,
+        /// Part of the pool reserves the shares are worth now.
+        pub elp_value: Balance,
+        pub elc_value: Balance,
+    }
 
     #[ink(storage)]
     pub struct Stable {
@@ -32,15 +586,38 @@ mod stable {
         last_expand_time: u128,
         // 上次ELC收缩时间
         last_contract_time: u128,
-        // 两次扩张或收缩时间间隔
-        adjust_gap: u128,
+        // 负债率阈值、增发分配比例、收缩区间、两次扩张或收缩时间间隔等参数
+        params: Parameters,
         elc_contract: Lazy<ELCRef>,
         relp_contract: Lazy<RELPRef>,
         govern_contract: Lazy<GovernRef>,
         oracle_contract: Lazy<OracleRef>,
         add_contract: Lazy<AdditionalRef>,
-        exchange_contract: Lazy<PatraExchange2Ref>,
+        // 交易所适配合约，通过SwapRef按selector调用
         exchange_accountid: AccountId,
+        govern_accountid: AccountId,
+        // 调整ELC价格的调用者奖励，按兑换的ELP计算，base 10000
+        keeper_bounty_rate: u128,
+        // 单次调整奖励上限
+        keeper_bounty_cap: u128,
+        // 紧急关停权限，治理合约之外可触发关停的账户
+        guardian: Option<AccountId>,
+        // 关停后的全局清算信息
+        settlement: Option<Settlement>,
+        // 当前赎回窗口的开始时间，窗口长度为adjust_gap
+        redeem_window_start: u128,
+        // 当前窗口已赎回的ELC
+        redeemed_in_window: Balance,
+        // 白名单抵押资产，token地址 => 抵押信息
+        collaterals: StorageHashMap<AccountId, Collateral>,
+        // 关停后各抵押资产的清算分配，token地址 => 分配信息
+        collateral_pots: StorageHashMap<AccountId, CollateralPot>,
+        // 进行中的补充资本拍卖
+        auction: Option<Auction>,
+        // 协议自有流动性：持有的交易池份额，及其投入的ELP和增发的ELC
+        pol_shares: Balance,
+        pol_elp: Balance,
+        pol_elc: Balance,
     }
 
     #[ink(event)]
@@ -53,6 +630,7 @@ mod stable {
         relp_amount: u128,
         #[ink(topic)]
         elc_amount: u128,
+        fee: u128,
     }
 
     #[ink(event)]
@@ -65,6 +643,7 @@ mod stable {
         elc_amount: u128,
         #[ink(topic)]
         elp_amount: u128,
+        fee: u128,
     }
 
     #[ink(event)]
@@ -91,6 +670,124 @@ mod stable {
         reserve_consumed: u128,
     }
 
+    #[ink(event)]
+    pub struct Shutdown {
+        #[ink(topic)]
+        caller: AccountId,
+        elc_price: u128,
+        elp_price: u128,
+        elc_pot: u128,
+        relp_pot: u128,
+    }
+
+    #[ink(event)]
+    pub struct SettlementRedeemed {
+        #[ink(topic)]
+        user: AccountId,
+        elc_amount: u128,
+        relp_amount: u128,
+        elp_amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct SettlementCollateralPaid {
+        #[ink(topic)]
+        user: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct ElcRedeemed {
+        #[ink(topic)]
+        user: AccountId,
+        elc_amount: u128,
+        elp_amount: u128,
+        fee: u128,
+    }
+
+    #[ink(event)]
+    pub struct ParameterChanged {
+        old: Parameters,
+        new: Parameters,
+    }
+
+    /// Event emitted when the caller of `expand_elc` or `contract_elc` is paid.
+    #[ink(event)]
+    pub struct KeeperRewarded {
+        #[ink(topic)]
+        keeper: AccountId,
+        elp_traded: u128,
+        bounty: u128,
+    }
+
+    #[ink(event)]
+    pub struct CollateralUpdated {
+        #[ink(topic)]
+        token: AccountId,
+        oracle: AccountId,
+        haircut: u128,
+        debt_ceiling: u128,
+    }
+
+    #[ink(event)]
+    pub struct AddCollateralLiquidity {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        amount: u128,
+        relp_amount: u128,
+        elc_amount: u128,
+        fee: u128,
+    }
+
+    #[ink(event)]
+    pub struct RecapAuctionStarted {
+        target: u128,
+        start_price: u128,
+        floor_price: u128,
+        duration: u128,
+    }
+
+    #[ink(event)]
+    pub struct RecapBid {
+        #[ink(topic)]
+        bidder: AccountId,
+        elp_amount: u128,
+        relp_amount: u128,
+        price: u128,
+    }
+
+    #[ink(event)]
+    pub struct RecapAuctionSettled {
+        raised: u128,
+        relp_sold: u128,
+    }
+
+    #[ink(event)]
+    pub struct PolDeployed {
+        elp_amount: u128,
+        elc_amount: u128,
+        shares: u128,
+    }
+
+    #[ink(event)]
+    pub struct PolWithdrawn {
+        elp_amount: u128,
+        elc_amount: u128,
+        shares: u128,
+    }
+
+    #[ink(event)]
+    pub struct FeesAbsorbed {
+        #[ink(topic)]
+        relp_amount: u128,
+        #[ink(topic)]
+        elp_amount: u128,
+    }
+
     impl Stable {
         #[ink(constructor)]
         pub fn new (
@@ -106,7 +803,6 @@ mod stable {
             let govern_contract: GovernRef = FromAccountId::from_account_id(govern_addr);
             let oracle_contract: OracleRef = FromAccountId::from_account_id(oracle_addr);
             let add_contract: AdditionalRef = FromAccountId::from_account_id(additional_addr);
-            let exchange_contract: PatraExchange2Ref = FromAccountId::from_account_id(exchange_addr);
             let blocktime = Self::env().block_timestamp().into();
             let instance = Self {
                 reserve: 0,
@@ -115,384 +811,1443 @@ mod stable {
                 elc_reserve_source: 0,
                 last_expand_time:  blocktime,
                 last_contract_time:  blocktime,
-                adjust_gap: 3600 * 24 * 1000, // one hour
+                params: Parameters::default(),
                 elc_contract: Lazy::new(elc_contract),
                 relp_contract: Lazy::new(relp_contract),
                 govern_contract: Lazy::new(govern_contract),
                 oracle_contract: Lazy::new(oracle_contract),
                 add_contract: Lazy::new(add_contract),
-                exchange_contract: Lazy::new(exchange_contract),
                 exchange_accountid: exchange_addr,
+                govern_accountid: govern_addr,
+                keeper_bounty_rate: 0,
+                keeper_bounty_cap: 0,
+                guardian: None,
+                settlement: None,
+                redeem_window_start: blocktime,
+                redeemed_in_window: 0,
+                collaterals: StorageHashMap::new(),
+                collateral_pots: StorageHashMap::new(),
+                auction: None,
+                pol_shares: 0,
+                pol_elp: 0,
+                pol_elc: 0,
             };
             instance
         }
 
-        /// add liquidity for ELP，returns rELP and ELC
+        /// add liquidity for ELP，returns rELP and ELC. `mint_fee` of the ELP goes to risk reserve.
+        /// Fails and refunds the ELP when less than `min_relp_out` rELP or `min_elc_out` ELC
+        /// would be minted, or the block time is past `deadline`. Panics when a mint fails,
+        /// which returns the ELP with the reverted call.
         #[ink(message, payable)]
-        pub fn add_liquidity(&mut self) -> (Balance, Balance) {
+        pub fn add_liquidity(
+            &mut self,
+            min_relp_out: Balance,
+            min_elc_out: Balance,
+            deadline: u128,
+        ) -> Result<(Balance, Balance)> {
             let caller: AccountId = self.env().caller();
             let elp_amount: Balance = self.env().transferred_balance();
-            let (relp_tokens, elc_tokens) = self.compute_liquidity(elp_amount);
+            let checked = self.check_live()
+                .and_then(|_| check_deadline(self.env().block_timestamp().into(), deadline))
+                .and_then(|_| self.liquidity_fee_rates())
+                .and_then(|(mint_rate, _)| {
+                    let fee = fee_of(elp_amount, mint_rate);
+                    let (relp_tokens, elc_tokens) = self.compute_liquidity(elp_amount - fee)?;
+                    check_min_out(relp_tokens, min_relp_out)?;
+                    check_min_out(elc_tokens, min_elc_out)?;
+                    Ok((relp_tokens, elc_tokens, fee))
+                });
+            let (relp_tokens, elc_tokens, fee) = match checked {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    // 退还转入的ELP，退还失败时panic，转入的ELP随调用一起回滚
+                    if elp_amount > 0 {
+                        assert!(self.env().transfer(caller, elp_amount).is_ok(), "ELP refund failed");
+                    }
+                    return Err(error)
+                }
+            };
+            // 铸造失败时panic，转入的ELP随调用一起回滚
             if elc_tokens > 0 {
-                assert!(self
-                    .elc_contract
-                    .mint(caller, elc_tokens)
-                    .is_ok());
-            }
-            assert!(self
-                .relp_contract
-                .mint(caller, relp_tokens)
-                .is_ok());
+                assert!(self.elc_contract.mint(caller, elc_tokens).is_ok(), "ELC mint failed");
+            }
+            assert!(self.relp_contract.mint(caller, relp_tokens).is_ok(), "rELP mint failed");
             ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), AddLiquidity {
             // self.env().emit_event(AddLiquidity {
                 sender: caller,
                 elp_amount: elp_amount,
                 relp_amount: relp_tokens,
                 elc_amount: elc_tokens,
+                fee,
             });
-            self.reserve += elp_amount;
-            (relp_tokens, elc_tokens)
+            self.reserve += elp_amount - fee;
+            self.risk_reserve += fee;
+            Ok((relp_tokens, elc_tokens))
         }
 
         /// compute add-liquidity threshold for internal and external call
         #[ink(message)]
-        pub fn compute_liquidity(&self, elp_amount_deposit: Balance) -> (Balance, Balance) {
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
-
-            let mut relp_price = self.relp_price();
-            let lr = self.liability_ratio();
-            if lr < 30 {
-                let relp_tokens = elp_price * elp_amount_deposit * (100 - lr) / relp_price / 100;
-                let elc_tokens = elp_price * elp_amount_deposit * lr / elc_price / 100;
-                (relp_tokens, elc_tokens)
+        pub fn compute_liquidity(&self, elp_amount_deposit: Balance) -> Result<(Balance, Balance)> {
+            let (elc_price, elp_price) = self.prices()?;
+
+            let mut relp_price = self.relp_price()?;
+            let lr = self.liability_ratio()?;
+            let elp_value = mul(elp_price, elp_amount_deposit)?;
+            if lr < self.params.issue_ratio {
+                let relp_tokens = mul_div(elp_value, 100 - lr, mul(relp_price, 100)?)?;
+                let elc_tokens = mul_div(elp_value, lr, mul(elc_price, 100)?)?;
+                Ok((relp_tokens, elc_tokens))
             } else {
-                let relp_tokens = elp_price * elp_amount_deposit / relp_price;
-                (relp_tokens, 0)
+                let relp_tokens = elp_value / relp_price;
+                Ok((relp_tokens, 0))
             }
         }
 
         /// remove liquidity, user can redeem their ELP by burn rELP and ELC.
         /// elc amount will be calculated automatically by `relp_amount`.
+        /// Fails when more than `max_elc_in` ELC would be burned, less than `min_elp_out` ELP
+        /// redeemed, or the block time is past `deadline`. `remove_fee` of the ELP goes to
+        /// risk reserve, `min_elp_out` is checked after the fee. Panics when the rELP burn or
+        /// the ELP transfer fails after the ELC is burned, reverting the call.
         #[ink(message)]
-        pub fn remove_liquidity(&mut self, relp_amount: Balance) -> Balance {
-            let lr = self.liability_ratio();
-            assert!(lr > 90, "LR must be less than 90%");
-
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
+        pub fn remove_liquidity(
+            &mut self,
+            relp_amount: Balance,
+            max_elc_in: Balance,
+            min_elp_out: Balance,
+            deadline: u128,
+        ) -> Result<Balance> {
+            self.check_live()?;
+            check_deadline(self.env().block_timestamp().into(), deadline)?;
+            let lr = self.liability_ratio()?;
+            if lr <= self.params.remove_ratio {
+                return Err(Error::LiabilityRatioOutOfRange)
+            }
+            let (elc_price, elp_price) = self.prices()?;
 
             let caller = self.env().caller();
-            let relp_price = self.relp_price();
+            let relp_price = self.relp_price()?;
             let elc_need = relp_amount * relp_price * lr / elc_price / (100 - lr);
-            assert!(self.elc_contract.burn(caller, elc_need).is_ok());
-            assert!(self.relp_contract.burn(caller, relp_amount).is_ok());
-            
-            //redeem ELP
             let elp_amount = relp_amount * relp_price / elp_price / (100 - lr) / 100;
-            let caller: AccountId= self.env().caller();
-            assert!(self.env().transfer(caller, elp_amount).is_ok());
+            let fee = fee_of(elp_amount, self.params.remove_fee.rate(lr));
+            if elc_need > max_elc_in {
+                return Err(Error::SlippageExceeded)
+            }
+            check_min_out(elp_amount - fee, min_elp_out)?;
+            if elp_amount > self.reserve {
+                return Err(Error::InsufficientReserve)
+            }
+            if self.elc_contract.burn(caller, elc_need).is_err() {
+                return Err(Error::BurnFailed)
+            }
+            // ELC已销毁，之后的失败直接panic回滚整个调用
+            assert!(self.relp_contract.burn(caller, relp_amount).is_ok(), "rELP burn failed");
             self.reserve -= elp_amount;
+            self.risk_reserve += fee;
+
+            //redeem ELP
+            assert!(self.env().transfer(caller, elp_amount - fee).is_ok(), "ELP transfer failed");
             ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), RemoveLiquidity {
             // self.env().emit_event(RemoveLiquidity {
                 sender: caller,
                 relp_amount: relp_amount,
                 elc_amount: elc_need,
-                elp_amount: elp_amount,
+                elp_amount: elp_amount - fee,
+                fee,
             });
-            elp_amount
+            Ok(elp_amount - fee)
         }
 
-        /// when price higher:
-        /// 1.call swap contract, swap elc for elp
-        /// 2.raise ELC
+        /// Current `(mint_fee, remove_fee)` rates at the liability ratio, base 10000.
         #[ink(message)]
-        pub fn expand_elc(&mut self) {
-            let lr = self.liability_ratio();
-            assert!(lr < 70, "LR must be less than 70%");
-
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
-            let elcaim_deviation = self.govern_contract.elcaim(); //theory deviation is [elcaim * 98, elcaim]
-            assert!(elc_price > elcaim_deviation, "ELC price must higher than ELCaim");
-
-            // assert time > adjust duration
-            let block_time:u128 = self.env().block_timestamp().into();
-            let gap: u128 = block_time - self.last_expand_time;
-            assert!(gap >= self.adjust_gap, "Time between two elc expand need to longer than 24 hours");
-
-            let base: u128 = 10;
-            let decimals: u32 = self.elc_contract.token_decimals().unwrap_or(0).into();
-            // estimate ELC value: value per ELC in swap
-            let elp_amount_per_elc = self.exchange_contract.get_token_to_dot_input_price(base.pow(decimals.into()));
-            // TODO: 此处是否还需要乘以elp_price.
-            let value_per_elc = elp_amount_per_elc * elp_price;
-
-            // delta_elp = 0.1% * elp / (1- 0.1%) = elp / 999
-            // TODO: exchange_contrat does not have method `reserve_elp`.
-            // let delta_elp = self.exchange_contract.reserve_elp().saturating_mul(base.pow(decimals)) / 999;
-            let delta_elp = 100000000u128;  // TODO: just for pass compile, delete this line when finish the line above.
-            let delta_elc = delta_elp / value_per_elc;
-
-            let elc_risk = self.elc_risk_reserve_source;
-            let elc_reserve = self.elc_reserve_source;
-            let elc_reserve_all = elc_risk + elc_reserve;
-            
-            if (elc_reserve_all) >= delta_elc {
-                // 计算出兑换的elp量
-                let elp_amount = self.exchange_contract.swap_token_to_dot_input(delta_elc);
-                // ELC兑换ELP
-                self.swap_elp_with_elc(delta_elc, elc_reserve, elc_risk, elp_amount); 
-            } else {
-                // 先将所有的elc reserve and elc risk交换成elp
-                let elp_amount = self.exchange_contract.swap_token_to_dot_input(elc_reserve_all);
-                self.swap_elp_with_elc(elc_reserve_all, elc_reserve, elc_risk, elp_amount); 
-
-                // ELC增发，当elc_reserve_all用完后，可适量进行ELC增发
-                let elc_balance: u128 = self.elc_contract.total_supply();
-                // 理论增发量
-                let mut issue = (elc_price - elcaim_deviation) * elc_balance / elcaim_deviation;
-
-                // 将所有储备ELC和风险准备ELC均换成ELP后，剩下还需要兑换的量
-                // 根据需求: 理论增发量的5% == delta_elc - elc_reserve_all, 即剩余增发量=(result of sub) * 20.
-                let remain_elc = (delta_elc- elc_reserve_all) * 20;
-
-                let now_time = self.env().block_timestamp().into();
-                // elc reserve is zero, do elc additional issue work.
-                if elc_reserve_all == 0 {
-                    // reward to relp holders.
-                    // TODO: use `let PART95 ＝ AMOUNT * 95 %` and `AMOUNT - PART95`
-                    let (reward_elc, swap_elc) = (issue * 95 / 100, issue * 5 / 100);
-
-                    let total_coinday = self.update_total_coinday(now_time);
-                    // assert!(self.add_contract.update_awards(award_info).is_ok());
-                    // TODO: 95％的增发量，使用elc.mint(relp_contract_addr, amount)铸elc给relp合约地址，当各用户根据
-                    // relp token持有币天来清算elc时，直接在relp合约中transfer即可, 这样似乎用不着additional合约了
-                    assert!(self.add_contract.update_awards(reward_elc, total_coinday, now_time).is_ok());
-
-                    // ELC swap to ELP
-                    let elp_amount = self.exchange_contract.swap_token_to_dot_input(swap_elc);
-                    self.swap_elp_with_elc(swap_elc, 0, 0, elp_amount);
-                } else {
-                    if issue > remain_elc {
-                        issue = remain_elc;
-                    }
-                    // reward to relp holders.
-                    let (reward_elc, swap_elc) = (issue * 95 / 100, issue * 5 / 100);
-
-                    let total_coinday = self.update_total_coinday(now_time);
-                    // assert!(self.add_contract.update_awards(award_info).is_ok());
-                    assert!(self.add_contract.update_awards(reward_elc, total_coinday, now_time).is_ok());
+        pub fn liquidity_fee_rates(&self) -> Result<(u128, u128)> {
+            let lr = self.liability_ratio()?;
+            Ok((self.params.mint_fee.rate(lr), self.params.remove_fee.rate(lr)))
+        }
 
-                    // ELC swap to ELP
-                    let elp_amount = self.exchange_contract.swap_token_to_dot_input(swap_elc);
-                    self.swap_elp_with_elc(swap_elc, 0, 0, elp_amount);
-                }
+        /// add liquidity for `amount` of a whitelisted collateral `token`, returns rELP and ELC.
+        /// The token is valued at its oracle price after the haircut, `mint_fee` of it goes to
+        /// the risk reserve of the collateral. The caller must approve the token first.
+        #[ink(message)]
+        pub fn add_liquidity_with(
+            &mut self,
+            token: AccountId,
+            amount: Balance,
+            min_relp_out: Balance,
+            min_elc_out: Balance,
+            deadline: u128,
+        ) -> Result<(Balance, Balance)> {
+            self.check_live()?;
+            check_deadline(self.env().block_timestamp().into(), deadline)?;
+            let mut collateral = self.collaterals.get(&token).cloned().ok_or(Error::UnknownCollateral)?;
+            if amount == 0 {
+                return Err(Error::InvalidAmount)
             }
-            self.last_expand_time = block_time;
-        }
+            let (mint_rate, _) = self.liquidity_fee_rates()?;
+            let fee = fee_of(amount, mint_rate);
+            if collateral.reserve + amount - fee > collateral.debt_ceiling {
+                return Err(Error::DebtCeilingExceeded)
+            }
+            let (_, elp_price) = self.prices()?;
+            let elp_value = self.collateral_in_elp(token, &collateral, amount - fee, elp_price)?;
+            let (relp_tokens, elc_tokens) = self.compute_liquidity(elp_value)?;
+            check_min_out(relp_tokens, min_relp_out)?;
+            check_min_out(elc_tokens, min_elc_out)?;
 
-        // when price lower, call swap contract, swap elp for elc
-        #[ink(message, payable)]
-        pub fn contract_elc(&mut self){
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
-            let elcaim_deviation = self.govern_contract.elcaim(); //theory deviation is [elcaim * 98, elcaim]
-            assert!(elc_price < elcaim_deviation * 98 / 100, "ELC price must lower than ELCaim * 98%");
-
-            // assert time > adjust duration
-            let block_time:u128 = self.env().block_timestamp().into();
-            let gap: u128 = block_time - self.last_expand_time;
-            assert!(gap >= self.adjust_gap, "Time between two elc contract need to longer than 24 hours");
-
-            let base: u128 = 10;
-            // estimate ELC value: value per ELC in swap
-            let decimals: u32 = self.elc_contract.token_decimals().unwrap_or(0).into();
-            // estimate ELC value: value per ELC in swap
-            let elc_amount_per_elp = self.exchange_contract.get_dot_to_token_input_price(base.pow(decimals.into()));
-            let value_per_elp = elc_amount_per_elp * elc_price;
-
-            // 计算兑换量
-            // 将价格冲击放大10000倍，用于防止计算出现小数
-            // TODO: exchange_contract 没有reserve_elc()这个方法
-            // let delta_price_impact = (elcaim_deviation * 98 / 100 - elc_price) * 10000 / elc_price;
-            // let delta_elc = self.exchange_contract.reserve_elc().saturating_mul(base.pow(decimals)) / (10000 - delta_price_impact) / delta_price_impact;
-            let delta_elc = 100000000u128;  // TODO: just for pass compile, delete this line when finish the two line above.
-            let mut delta_elp = delta_elc / value_per_elp;
-
-            let mut elp_risk = self.risk_reserve;
-            let elp_reserve = self.reserve;
-
-            if elp_risk > 0 {
-                if delta_elp > elp_risk {
-                    delta_elp = elp_risk;
-                } 
-                assert!(self.env().transfer(self.exchange_accountid, delta_elp).is_ok());
-                let elc_amount = self.exchange_contract.swap_dot_to_token_input();
-                self.swap_elc_with_elp(delta_elp, 0, elc_amount);
-            } else {
-                if delta_elp > elp_reserve * 2 / 100 {
-                    delta_elp = elp_reserve * 2 / 100;
-                }
-                assert!(self.env().transfer(self.exchange_accountid, delta_elp).is_ok());
-                let elc_amount = self.exchange_contract.swap_dot_to_token_input();
-                self.swap_elc_with_elp(0, delta_elp, elc_amount);
+            let caller = self.env().caller();
+            let mut token_contract = PSP22Ref::new(token);
+            if token_contract.transfer_from(caller, self.env().account_id(), amount, Vec::new()).is_err() {
+                return Err(Error::TransferFailed)
             }
-            self.last_contract_time = block_time;
+            collateral.reserve += amount - fee;
+            collateral.risk_reserve += fee;
+            self.collaterals.insert(token, collateral);
+            // 抵押资产已转入，铸造失败时panic回滚整个调用
+            if elc_tokens > 0 {
+                assert!(self.elc_contract.mint(caller, elc_tokens).is_ok(), "ELC mint failed");
+            }
+            assert!(self.relp_contract.mint(caller, relp_tokens).is_ok(), "rELP mint failed");
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), AddCollateralLiquidity {
+                sender: caller,
+                token,
+                amount,
+                relp_amount: relp_tokens,
+                elc_amount: elc_tokens,
+                fee,
+            });
+            Ok((relp_tokens, elc_tokens))
         }
 
-        /// compute liability ratio
+        /// Whitelist `token` or update its oracle, haircut and debt ceiling, the reserves of an
+        /// existing collateral are kept. Only callable by govern contract.
         #[ink(message)]
-        pub fn liability_ratio(&self) -> u128 {
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_amount: u128 = self.reserve;
-            let elc_amount: Balance = self.elc_contract.total_supply();
-            let lr =  elc_amount * elc_price * 100 /(elp_price * elp_amount); //100 as base
-            if lr > 100 {
-                return 100
-            } else if lr < 1 {
-                return 1
+        pub fn set_collateral(
+            &mut self,
+            token: AccountId,
+            oracle: AccountId,
+            haircut: u128,
+            debt_ceiling: Balance,
+        ) -> Result<()> {
+            self.only_govern()?;
+            if haircut >= 100 {
+                return Err(Error::InvalidParameters)
             }
-            lr 
+            let (reserve, risk_reserve) = self.collaterals.get(&token)
+                .map(|c| (c.reserve, c.risk_reserve))
+                .unwrap_or((0, 0));
+            self.collaterals.insert(token, Collateral { oracle, haircut, debt_ceiling, reserve, risk_reserve });
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), CollateralUpdated {
+                token,
+                oracle,
+                haircut,
+                debt_ceiling,
+            });
+            Ok(())
         }
 
-        ///compute internal relp price for query
         #[ink(message)]
-        pub fn relp_price(&self) -> u128 {
-            let elc_price: u128 = self.oracle_contract.elc_price();
-            assert!(elc_price > 0, "ELC price is zero, check oracle functionality first!");
-            let elp_price: u128 = self.oracle_contract.elp_price();
-            assert!(elp_price > 0, "ELP price is zero, check oracle functionality first!");
-            let elc_supply: Balance = self.elc_contract.total_supply();
-            let relp_supply = self.relp_contract.total_supply();
-            if relp_supply > 0 {
-                //p(rELP) = (p(ELP)*Amount(ELP) - p(ELC)*Totalsupply(ELC)) / Amount(rELP)
-               let relp_price = (elp_price * self.reserve - elc_price * elc_supply) / relp_supply;
-               relp_price
-            } else {
-                // initial
-                elp_price
-            }
+        pub fn collateral(&self, token: AccountId) -> Option<Collateral> {
+            self.collaterals.get(&token).cloned()
         }
 
-        /// Do not direct tranfer ELP to deployed pool address, use this function
-        #[ink(message, payable)]
-        pub fn add_risk_reserve(&mut self) {
-            let elp_amount: Balance = self.env().transferred_balance();
-            self.risk_reserve += elp_amount;
+        #[ink(message)]
+        pub fn collateral_tokens(&self) -> Vec<AccountId> {
+            self.collaterals.keys().copied().collect()
         }
 
+        /// ELP worth of the whole reserve basket, ELP plus collaterals after haircuts.
         #[ink(message)]
-        pub fn elp_reserve(&self) -> u128 { self.reserve }
+        pub fn reserve_value(&self) -> Result<Balance> {
+            Ok(self.reserve + self.collateral_value()?)
+        }
 
+        /// Burn `elc_amount` ELC of the caller for ELP at ELCaim price minus `redeem_fee`.
+        /// Only when the liability ratio is below `redeem_ratio`, at most `redeem_cap` of the
+        /// ELC supply is redeemed per `adjust_gap`. The fee moves to risk reserve. Returns the ELP
+        /// paid.
         #[ink(message)]
-        pub fn elp_risk_reserve(&self) -> u128 { self.risk_reserve }
+        pub fn redeem_elc(&mut self, elc_amount: Balance) -> Result<Balance> {
+            self.check_live()?;
+            if elc_amount == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            let lr = self.liability_ratio()?;
+            if lr >= self.params.redeem_ratio {
+                return Err(Error::LiabilityRatioOutOfRange)
+            }
+            if elc_amount > self.redeemable_elc() {
+                return Err(Error::RedeemLimitExceeded)
+            }
+            let (_, elp_price) = self.prices()?;
+            let elcaim = self.govern_contract.current_elcaim();
+            let (elp_amount, fee) = redeem_value(elc_amount, elcaim, elp_price, self.params.redeem_fee);
+            if elp_amount + fee > self.reserve {
+                return Err(Error::InsufficientReserve)
+            }
+
+            let caller = self.env().caller();
+            if self.elc_contract.burn(caller, elc_amount).is_err() {
+                return Err(Error::BurnFailed)
+            }
+            // ELC已销毁，转账失败时panic回滚整个调用
+            assert!(self.env().transfer(caller, elp_amount).is_ok(), "ELP transfer failed");
+            self.reserve -= elp_amount + fee;
+            self.risk_reserve += fee;
+            let block_time: u128 = self.env().block_timestamp().into();
+            if block_time.saturating_sub(self.redeem_window_start) >= self.params.adjust_gap {
+                self.redeem_window_start = block_time;
+                self.redeemed_in_window = 0;
+            }
+            self.redeemed_in_window += elc_amount;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ElcRedeemed {
+                user: caller,
+                elc_amount,
+                elp_amount,
+                fee,
+            });
+            Ok(elp_amount)
+        }
 
+        /// ELC still redeemable by `redeem_elc` in the current window.
         #[ink(message)]
-        pub fn elc_reserve(&self) -> u128 { self.elc_reserve_source }
+        pub fn redeemable_elc(&self) -> Balance {
+            let limit = self.elc_contract.total_supply() * self.params.redeem_cap / 10000;
+            let block_time: u128 = self.env().block_timestamp().into();
+            if block_time.saturating_sub(self.redeem_window_start) >= self.params.adjust_gap {
+                return limit
+            }
+            limit.saturating_sub(self.redeemed_in_window)
+        }
 
+        /// when price higher:
+        /// 1.call swap contract, swap elc for elp
+        /// 2.raise ELC
+        /// Returns the keeper bounty paid to the caller.
         #[ink(message)]
-        pub fn elc_risk_reserve(&self) -> u128 { self.elc_risk_reserve_source }
+        pub fn expand_elc(&mut self) -> Result<Balance> {
+            self.check_live()?;
+            let (ratio_ok, price_ok, gap_ok) = self.expand_checks()?;
+            if !ratio_ok {
+                return Err(Error::LiabilityRatioOutOfRange)
+            }
+            if !price_ok {
+                return Err(Error::ElcPriceInRange)
+            }
+            if !gap_ok {
+                return Err(Error::AdjustGapNotElapsed)
+            }
+            let block_time: u128 = self.env().block_timestamp().into();
+            let (from_reserves, issue) = self.plan_expand()?;
+            let (elc_risk, elc_reserve) = (self.elc_risk_reserve_source, self.elc_reserve_source);
 
-        fn swap_elp_with_elc(
+            // 先将elc reserve and elc risk交换成elp
+            let mut elp_traded = 0;
+            if from_reserves > 0 {
+                let elp_amount = self.swap_elc(from_reserves)?;
+                self.swap_elp_with_elc(from_reserves, elc_reserve, elc_risk, elp_amount);
+                elp_traded += elp_amount;
+            }
+
+            // ELC增发，当elc_reserve_all用完后，可适量进行ELC增发
+            if let Some(issue) = issue {
+                let now_time = self.env().block_timestamp().into();
+                // reward to relp holders.
+                let (reward_elc, swap_elc) = split_issue(issue, self.params.reward_share);
+
+                // TODO: 95％的增发量，使用elc.mint(relp_contract_addr, amount)铸elc给relp合约地址，当各用户根据
+                // relp token持有币天来清算elc时，直接在relp合约中transfer即可, 这样似乎用不着additional合约了
+                let coinday_supply = match self.relp_contract.coinday_supply() {
+                    Ok(coinday_supply) => coinday_supply,
+                    // 储备ELC已卖出，之后的失败直接panic回滚整个调用
+                    Err(_) if from_reserves > 0 => panic!("Reading coinday supply failed"),
+                    Err(_) => return Err(Error::Overflow),
+                };
+                // 奖励发布后的失败直接panic回滚整个调用
+                assert!(
+                    Distribution::<Elc>::publish(&mut *self.add_contract, reward_elc, coinday_supply, now_time).is_ok(),
+                    "Publishing ELC reward failed");
+
+                // ELC swap to ELP
+                assert!(swap_elc == 0 || self.elc_contract.mint(self.env().account_id(), swap_elc).is_ok(), "ELC mint failed");
+                let elp_amount = self.swap_elc(swap_elc).expect("Selling issued ELC failed");
+                self.swap_elp_with_elc(swap_elc, 0, 0, elp_amount);
+                elp_traded += elp_amount;
+            }
+            self.last_expand_time = block_time;
+            Ok(self.pay_keeper(elp_traded))
+        }
+
+        /// Dry run of `expand_elc` at the current state, the ELP amounts are quoted by exchange.
+        #[ink(message)]
+        pub fn preview_expand(&self) -> Result<AdjustPreview> {
+            let (ratio_ok, price_ok, gap_ok) = self.expand_checks()?;
+            let (from_reserves, issue) = self.plan_expand()?;
+            let issue = issue.unwrap_or(0);
+            let (_, swap_elc) = split_issue(issue, self.params.reward_share);
+
+            let elp_from_reserves = self.exchange().quote_elc_to_elp(from_reserves);
+            let elp_amount = self.exchange().quote_elc_to_elp(from_reserves + swap_elc);
+            let mut changes = expand_changes(
+                from_reserves, self.elc_reserve_source, self.elc_risk_reserve_source, elp_from_reserves);
+            changes.risk_reserve += (elp_amount - elp_from_reserves) as i128;
+
+            let keeper_bounty = self.keeper_bounty_of(elp_amount)
+                .min(shift(self.risk_reserve, changes.risk_reserve));
+            changes.risk_reserve -= keeper_bounty as i128;
+
+            let (elc_price, elp_price) = self.prices()?;
+            let liability_ratio = ratio(
+                self.elc_contract.total_supply() + issue,
+                shift(self.reserve, changes.reserve) + self.collateral_value()?,
+                elc_price,
+                elp_price,
+            );
+            Ok(AdjustPreview {
+                ratio_ok,
+                price_ok,
+                gap_ok,
+                elc_amount: from_reserves + swap_elc,
+                elp_amount,
+                elc_issued: issue,
+                keeper_bounty,
+                changes,
+                liability_ratio,
+            })
+        }
+
+        // when price lower, call swap contract, swap elp for elc
+        /// Returns the keeper bounty paid to the caller.
+        #[ink(message, payable)]
+        pub fn contract_elc(&mut self) -> Result<Balance> {
+            self.check_live()?;
+            let (price_ok, gap_ok) = self.contract_checks()?;
+            if !price_ok {
+                return Err(Error::ElcPriceInRange)
+            }
+            if !gap_ok {
+                return Err(Error::AdjustGapNotElapsed)
+            }
+            let block_time: u128 = self.env().block_timestamp().into();
+            // 收缩时先撤出协议自有流动性，ELP计入风险准备金，ELC销毁
+            let unwound = self.pol_shares > 0;
+            if unwound {
+                self.unwind_pol(self.pol_shares)?;
+            }
+            let (reserve_elp, _) = self.exchange().reserves();
+            let swapped = self.plan_contract(reserve_elp, self.risk_reserve)
+                .and_then(|(delta_elp, from_risk)| Ok((delta_elp, from_risk, self.swap_elp(delta_elp)?)));
+            let (delta_elp, from_risk, elc_amount) = match swapped {
+                Ok(swapped) => swapped,
+                // 流动性已撤出，之后的失败直接panic回滚整个调用
+                Err(_) if unwound => panic!("Selling ELP failed"),
+                Err(err) => return Err(err),
+            };
+            if from_risk {
+                self.swap_elc_with_elp(delta_elp, 0, elc_amount);
+            } else {
+                self.swap_elc_with_elp(0, delta_elp, elc_amount);
+            }
+            self.last_contract_time = block_time;
+            Ok(self.pay_keeper(delta_elp))
+        }
+
+        /// Dry run of `contract_elc` at the current state. The protocol-owned liquidity is
+        /// taken out of the pool first, the ELC amount is priced on the pool left.
+        #[ink(message)]
+        pub fn preview_contract(&self) -> Result<AdjustPreview> {
+            let (price_ok, gap_ok) = self.contract_checks()?;
+            let (reserve_elp, reserve_elc) = self.exchange().reserves();
+            let total_shares = self.exchange().total_shares();
+            let pol_elp = share_of(self.pol_shares, total_shares, reserve_elp);
+            let pol_elc = share_of(self.pol_shares, total_shares, reserve_elc);
+            let (reserve_elp, reserve_elc) = (reserve_elp - pol_elp, reserve_elc - pol_elc);
+            let (delta_elp, from_risk) = self.plan_contract(reserve_elp, self.risk_reserve + pol_elp)?;
+            let elc_amount = input_price(delta_elp, reserve_elp, reserve_elc);
+            let mut changes = if from_risk {
+                contract_changes(delta_elp, 0, elc_amount)
+            } else {
+                contract_changes(0, delta_elp, elc_amount)
+            };
+            // 撤出的ELP计入风险准备金，ELC销毁
+            changes.risk_reserve += pol_elp as i128;
+            let keeper_bounty = self.keeper_bounty_of(delta_elp)
+                .min(shift(self.risk_reserve, changes.risk_reserve));
+            changes.risk_reserve -= keeper_bounty as i128;
+
+            let (elc_price, elp_price) = self.prices()?;
+            let liability_ratio = ratio(
+                self.elc_contract.total_supply() - pol_elc,
+                shift(self.reserve, changes.reserve) + self.collateral_value()?,
+                elc_price,
+                elp_price,
+            );
+            Ok(AdjustPreview {
+                ratio_ok: true,
+                price_ok,
+                gap_ok,
+                elc_amount,
+                elp_amount: delta_elp,
+                elc_issued: 0,
+                keeper_bounty,
+                changes,
+                liability_ratio,
+            })
+        }
+
+        /// Dry run of `remove_liquidity(relp_amount)` at the current state.
+        #[ink(message)]
+        pub fn preview_remove_liquidity(&self, relp_amount: Balance) -> Result<RemovePreview> {
+            let lr = self.liability_ratio()?;
+            let (elc_price, elp_price) = self.prices()?;
+            let relp_price = self.relp_price()?;
+            let (elc_in, elp_out) = if lr < 100 {
+                (
+                    relp_amount * relp_price * lr / elc_price / (100 - lr),
+                    relp_amount * relp_price / elp_price / (100 - lr) / 100,
+                )
+            } else {
+                (0, 0)
+            };
+            let fee = fee_of(elp_out, self.params.remove_fee.rate(lr));
+            let elc_supply = self.elc_contract.total_supply();
+            Ok(RemovePreview {
+                ratio_ok: lr > self.params.remove_ratio,
+                elc_in,
+                elp_out: elp_out - fee,
+                fee,
+                changes: ReserveChanges {
+                    reserve: -(elp_out as i128),
+                    risk_reserve: fee as i128,
+                    ..Default::default()
+                },
+                liability_ratio: ratio(
+                    elc_supply.saturating_sub(elc_in),
+                    self.reserve.saturating_sub(elp_out) + self.collateral_value()?,
+                    elc_price,
+                    elp_price,
+                ),
+            })
+        }
+
+        /// compute liability ratio
+        #[ink(message)]
+        pub fn liability_ratio(&self) -> Result<u128> {
+            let (elc_price, elp_price) = self.prices()?;
+            Ok(ratio(self.elc_contract.total_supply(), self.reserve_value()?, elc_price, elp_price))
+        }
+
+        ///compute internal relp price for query
+        #[ink(message)]
+        pub fn relp_price(&self) -> Result<u128> {
+            let (elc_price, elp_price) = self.prices()?;
+            let elc_supply: Balance = self.elc_contract.total_supply();
+            let relp_supply = self.relp_contract.total_supply();
+            if relp_supply > 0 {
+                //p(rELP) = (p(ELP)*Amount(ELP) - p(ELC)*Totalsupply(ELC)) / Amount(rELP)
+                // 抵押资产按扣除折扣后的ELP价值计入
+                let assets = elp_price * self.reserve_value()?;
+                let liabilities = elc_price * elc_supply;
+                if assets <= liabilities {
+                    return Err(Error::InsufficientReserve)
+                }
+                Ok((assets - liabilities) / relp_supply)
+            } else {
+                // initial
+                Ok(elp_price)
+            }
+        }
+
+        /// Do not direct tranfer ELP to deployed pool address, use this function
+        #[ink(message, payable)]
+        pub fn add_risk_reserve(&mut self) {
+            let elp_amount: Balance = self.env().transferred_balance();
+            self.risk_reserve += elp_amount;
+        }
+
+        #[ink(message)]
+        pub fn elp_reserve(&self) -> u128 { self.reserve }
+
+        /// Burn the RELP transfer fees held by this contract and move the ELP backing them
+        /// from reserve to risk reserve, at most the whole reserve. RELP price is unchanged,
+        /// anyone may call it. Returns the ELP moved.
+        #[ink(message)]
+        pub fn absorb_relp_fees(&mut self) -> Result<Balance> {
+            self.check_live()?;
+            let relp_amount = self.relp_contract.balance_of(self.env().account_id());
+            if relp_amount == 0 {
+                return Ok(0)
+            }
+            let (_, elp_price) = self.prices()?;
+            // 储备不足时只转出剩余的储备
+            let elp_amount = core::cmp::min(mul_div(relp_amount, self.relp_price()?, elp_price)?, self.reserve);
+            let reserve = sub(self.reserve, elp_amount)?;
+            let risk_reserve = add(self.risk_reserve, elp_amount)?;
+            if self.relp_contract.burn(self.env().account_id(), relp_amount).is_err() {
+                return Err(Error::BurnFailed)
+            }
+            self.reserve = reserve;
+            self.risk_reserve = risk_reserve;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), FeesAbsorbed {
+                relp_amount,
+                elp_amount,
+            });
+            Ok(elp_amount)
+        }
+
+        /// Start a recapitalization auction raising `target` ELP, when the liability ratio is
+        /// above `remove_ratio` and risk reserve is below 1% of reserve. Only callable by
+        /// govern contract.
+        #[ink(message)]
+        pub fn start_recap_auction(
+            &mut self,
+            target: Balance,
+            start_price: u128,
+            floor_price: u128,
+            duration: u128,
+        ) -> Result<()> {
+            self.only_govern()?;
+            self.check_live()?;
+            if self.auction.is_some() {
+                return Err(Error::AuctionActive)
+            }
+            if target == 0 || floor_price == 0 || floor_price > start_price || duration == 0 {
+                return Err(Error::InvalidParameters)
+            }
+            let lr = self.liability_ratio()?;
+            if !needs_recap(lr, self.params.remove_ratio, self.risk_reserve, self.reserve) {
+                return Err(Error::RecapNotNeeded)
+            }
+            self.auction = Some(Auction {
+                start_time: self.env().block_timestamp().into(),
+                duration,
+                start_price,
+                floor_price,
+                target,
+                raised: 0,
+                relp_sold: 0,
+            });
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), RecapAuctionStarted {
+                target,
+                start_price,
+                floor_price,
+                duration,
+            });
+            Ok(())
+        }
+
+        /// Buy rELP with the ELP sent at the current auction price, returns the rELP minted.
+        /// ELP above the remaining target is refunded. Fails and refunds the ELP when less than
+        /// `min_relp_out` rELP would be minted.
+        #[ink(message, payable)]
+        pub fn bid_recap(&mut self, min_relp_out: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            let transferred = self.env().transferred_balance();
+            let block_time: u128 = self.env().block_timestamp().into();
+            let checked = self.check_live()
+                .and_then(|_| self.auction.clone().ok_or(Error::NoAuction))
+                .and_then(|auction| {
+                    if block_time > auction.start_time + auction.duration {
+                        return Err(Error::AuctionEnded)
+                    }
+                    let price = auction_price(&auction, block_time);
+                    let elp_amount = transferred.min(auction.target - auction.raised);
+                    let relp_amount = elp_amount * AUCTION_PRICE_BASE / price;
+                    if relp_amount == 0 {
+                        return Err(Error::InvalidAmount)
+                    }
+                    check_min_out(relp_amount, min_relp_out)?;
+                    Ok((auction, price, elp_amount, relp_amount))
+                });
+            let (mut auction, price, elp_amount, relp_amount) = match checked {
+                Ok(bid) => bid,
+                Err(error) => {
+                    // 退还转入的ELP，退还失败时panic，转入的ELP随调用一起回滚
+                    if transferred > 0 {
+                        assert!(self.env().transfer(caller, transferred).is_ok(), "ELP refund failed");
+                    }
+                    return Err(error)
+                }
+            };
+            // 先铸造再退还多余的ELP，任一失败都panic回滚整个调用
+            assert!(self.relp_contract.mint(caller, relp_amount).is_ok(), "rELP mint failed");
+            if transferred > elp_amount {
+                assert!(self.env().transfer(caller, transferred - elp_amount).is_ok(), "ELP refund failed");
+            }
+            self.reserve += elp_amount;
+            auction.raised += elp_amount;
+            auction.relp_sold += relp_amount;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), RecapBid {
+                bidder: caller,
+                elp_amount,
+                relp_amount,
+                price,
+            });
+            if auction.raised >= auction.target {
+                self.settle_auction(auction);
+            } else {
+                self.auction = Some(auction);
+            }
+            Ok(relp_amount)
+        }
+
+        /// Close the auction after its duration, anyone can call. Returns the ELP raised.
+        #[ink(message)]
+        pub fn settle_recap_auction(&mut self) -> Result<Balance> {
+            let auction = self.auction.clone().ok_or(Error::NoAuction)?;
+            let block_time: u128 = self.env().block_timestamp().into();
+            if block_time <= auction.start_time + auction.duration {
+                return Err(Error::AuctionNotEnded)
+            }
+            let raised = auction.raised;
+            self.settle_auction(auction);
+            Ok(raised)
+        }
+
+        #[ink(message)]
+        pub fn recap_auction(&self) -> Option<Auction> {
+            self.auction.clone()
+        }
+
+        /// Current auction price, ELP per rELP with decimals 100000.
+        #[ink(message)]
+        pub fn recap_price(&self) -> Result<u128> {
+            let auction = self.auction.as_ref().ok_or(Error::NoAuction)?;
+            Ok(auction_price(auction, self.env().block_timestamp().into()))
+        }
+
+        /// Put `elp_amount` ELP of risk reserve and newly issued ELC at the pool price into the
+        /// exchange pool, returns the pool shares got. Only callable by govern contract.
+        #[ink(message)]
+        pub fn deploy_liquidity(&mut self, elp_amount: Balance) -> Result<Balance> {
+            self.only_govern()?;
+            self.check_live()?;
+            if elp_amount == 0 || elp_amount > self.risk_reserve {
+                return Err(Error::InvalidAmount)
+            }
+            if (self.pol_elp + elp_amount) * 100 > (self.risk_reserve + self.pol_elp) * self.params.pol_share {
+                return Err(Error::PolCapExceeded)
+            }
+            let (reserve_elp, reserve_elc) = self.exchange().reserves();
+            if reserve_elp == 0 || reserve_elc == 0 {
+                return Err(Error::InsufficientLiquidity)
+            }
+            let elc_amount = elp_amount * reserve_elc / reserve_elp;
+            if self.elc_contract.mint(self.env().account_id(), elc_amount).is_err() {
+                return Err(Error::MintFailed)
+            }
+            // ELC已铸造，之后的失败直接panic回滚整个调用
+            assert!(self.elc_contract.approve(self.exchange_accountid, elc_amount).is_ok(), "ELC approve failed");
+            let shares = match provide_liquidity(&mut self.exchange(), elp_amount, elc_amount) {
+                Ok(shares) => shares,
+                Err(_) => panic!("Providing liquidity failed"),
+            };
+            self.risk_reserve -= elp_amount;
+            self.pol_shares += shares;
+            self.pol_elp += elp_amount;
+            self.pol_elc += elc_amount;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), PolDeployed {
+                elp_amount,
+                elc_amount,
+                shares,
+            });
+            Ok(shares)
+        }
+
+        /// Take `shares` of protocol-owned liquidity out of the pool, the ELP goes back to
+        /// risk reserve and the ELC is burned. Only callable by govern contract before shutdown,
+        /// which unwinds all of it.
+        #[ink(message)]
+        pub fn withdraw_liquidity(&mut self, shares: Balance) -> Result<(Balance, Balance)> {
+            self.only_govern()?;
+            self.check_live()?;
+            self.unwind_pol(shares)
+        }
+
+        #[ink(message)]
+        pub fn pol_position(&self) -> PolPosition {
+            let (reserve_elp, reserve_elc) = self.exchange().reserves();
+            let total_shares = self.exchange().total_shares();
+            PolPosition {
+                shares: self.pol_shares,
+                elp_deployed: self.pol_elp,
+                elc_deployed: self.pol_elc,
+                elp_value: share_of(self.pol_shares, total_shares, reserve_elp),
+                elc_value: share_of(self.pol_shares, total_shares, reserve_elc),
+            }
+        }
+
+        #[ink(message)]
+        pub fn elp_risk_reserve(&self) -> u128 { self.risk_reserve }
+
+        #[ink(message)]
+        pub fn elc_reserve(&self) -> u128 { self.elc_reserve_source }
+
+        #[ink(message)]
+        pub fn elc_risk_reserve(&self) -> u128 { self.elc_risk_reserve_source }
+
+        /// Returns `(elc_price, elp_price)` of oracle, zero price means the oracle is not fed.
+        fn prices(&self) -> Result<(u128, u128)> {
+            if let Some(settlement) = &self.settlement {
+                return Ok((settlement.elc_price, settlement.elp_price))
+            }
+            let elc_price = check_price(self.oracle_contract.elc_price())?;
+            let elp_price = check_price(self.oracle_contract.elp_price())?;
+            Ok((elc_price, elp_price))
+        }
+
+        fn exchange(&self) -> SwapRef {
+            SwapRef::new(self.exchange_accountid)
+        }
+
+        /// Sell `elc_amount` ELC of reserves in exchange, returns the ELP got.
+        fn swap_elc(&mut self, elc_amount: u128) -> Result<u128> {
+            if elc_amount == 0 {
+                return Ok(0)
+            }
+            if self.elc_contract.approve(self.exchange_accountid, elc_amount).is_err() {
+                return Err(Error::SwapFailed)
+            }
+            let mut exchange = self.exchange();
+            let min_elp = min_out(exchange.quote_elc_to_elp(elc_amount), SWAP_TOLERANCE);
+            sell_elc(&mut exchange, elc_amount, min_elp)
+        }
+
+        /// Sell `elp_amount` ELP of reserves in exchange, returns the ELC got.
+        fn swap_elp(&mut self, elp_amount: u128) -> Result<u128> {
+            let mut exchange = self.exchange();
+            let min_elc = min_out(exchange.quote_elp_to_elc(elp_amount), SWAP_TOLERANCE);
+            sell_elp(&mut exchange, elp_amount, min_elc)
+        }
+
+        #[ink(message)]
+        pub fn parameters(&self) -> Parameters {
+            self.params.clone()
+        }
+
+        /// Only callable by govern contract.
+        #[ink(message)]
+        pub fn set_parameters(&mut self, params: Parameters) -> Result<()> {
+            self.only_govern()?;
+            if !params.is_valid() {
+                return Err(Error::InvalidParameters)
+            }
+            let old = core::mem::replace(&mut self.params, params.clone());
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ParameterChanged {
+                old,
+                new: params,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn guardian(&self) -> Option<AccountId> {
+            self.guardian
+        }
+
+        /// Only callable by govern contract.
+        #[ink(message)]
+        pub fn set_guardian(&mut self, guardian: Option<AccountId>) -> Result<()> {
+            self.only_govern()?;
+            self.guardian = guardian;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn settlement(&self) -> Option<Settlement> {
+            self.settlement.clone()
+        }
+
+        /// Settlement split of collateral `token`, `None` before shutdown.
+        #[ink(message)]
+        pub fn collateral_pot(&self, token: AccountId) -> Option<CollateralPot> {
+            self.collateral_pots.get(&token).cloned()
+        }
+
+        /// Emergency shutdown, callable by govern contract or the guardian. Prices are frozen
+        /// at the last oracle values, liquidity and ELC adjustments stop, and the ELP and
+        /// collateral reserves are split for the settlement redemptions, each in the proportion
+        /// of the ELC supply value to the whole basket. Protocol-owned liquidity is withdrawn
+        /// first if the exchange allows it, otherwise its shares are recorded in the settlement.
+        /// The ELC held in reserves is burned.
+        #[ink(message)]
+        pub fn shutdown(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if caller != self.govern_accountid && Some(caller) != self.guardian {
+                return Err(Error::OnlyGuardianAccess)
+            }
+            self.check_live()?;
+            let (elc_price, elp_price) = self.prices()?;
+            // 尽量先收回协议自有流动性，ELP计入风险储备，ELC销毁；收回失败时份额记入清算
+            let pol_shares = self.pol_shares;
+            let unwound = pol_shares > 0 && self.unwind_pol(pol_shares).is_ok();
+            let pol_shares = if unwound { 0 } else { pol_shares };
+
+            let stable = self.env().account_id();
+            let elc_held = self.elc_contract.balance_of(stable);
+            if elc_held > 0 && self.elc_contract.burn(stable, elc_held).is_err() {
+                // 流动性已收回，销毁失败时panic回滚整个调用
+                assert!(!unwound, "ELC burn failed");
+                return Err(Error::BurnFailed)
+            }
+            let elc_supply = self.elc_contract.total_supply();
+            let relp_supply = self.relp_contract.total_supply() - self.relp_contract.balance_of(stable);
+            let elp_total = self.reserve + self.risk_reserve;
+            // 抵押资产按同一比例分配，无法报价的抵押资产不计入总价值
+            let mut collateral_totals = Vec::new();
+            let mut total_value = elp_total;
+            for (token, collateral) in self.collaterals.iter() {
+                let amount = collateral.reserve + collateral.risk_reserve;
+                if amount > 0 {
+                    total_value += self.collateral_in_elp(*token, collateral, amount, elp_price).unwrap_or(0);
+                    collateral_totals.push((*token, amount));
+                }
+            }
+            let (elc_value, _) = settlement_pots(total_value, elc_supply, elc_price, elp_price);
+            let (elc_pot, relp_pot) = split_pot(elp_total, elc_value, total_value)?;
+            for (token, amount) in collateral_totals {
+                let (elc_pot, relp_pot) = split_pot(amount, elc_value, total_value)?;
+                self.collateral_pots.insert(token, CollateralPot { elc_pot, relp_pot, ..CollateralPot::default() });
+                let collateral = self.collaterals.get_mut(&token).expect("collateral listed above");
+                collateral.reserve = 0;
+                collateral.risk_reserve = 0;
+            }
+
+            self.reserve = 0;
+            self.risk_reserve = 0;
+            self.elc_reserve_source = 0;
+            self.elc_risk_reserve_source = 0;
+            self.settlement = Some(Settlement {
+                time: self.env().block_timestamp().into(),
+                elc_price,
+                elp_price,
+                elc_supply,
+                relp_supply,
+                elc_pot,
+                relp_pot,
+                elc_paid: 0,
+                relp_paid: 0,
+                pol_shares,
+            });
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), Shutdown {
+                caller,
+                elc_price,
+                elp_price,
+                elc_pot,
+                relp_pot,
+            });
+            Ok(())
+        }
+
+        /// Burn `elc_amount` ELC of the caller for its share of the ELC settlement pots, the
+        /// ELP and each collateral. Returns the ELP paid.
+        #[ink(message)]
+        pub fn redeem_elc_settlement(&mut self, elc_amount: Balance) -> Result<Balance> {
+            let mut settlement = self.settlement.clone().ok_or(Error::NotShutdown)?;
+            let elp_amount = settlement.redeem_elc(elc_amount)?;
+            let collateral_amounts = self.take_collateral_pots(|pot| {
+                take_pro_rata(elc_amount, settlement.elc_supply, pot.elc_pot, &mut pot.elc_paid)
+            })?;
+            let caller = self.env().caller();
+            if self.elc_contract.burn(caller, elc_amount).is_err() {
+                return Err(Error::BurnFailed)
+            }
+            self.settlement = Some(settlement);
+            // ELC已销毁，转账失败时panic回滚整个调用
+            assert!(self.env().transfer(caller, elp_amount).is_ok(), "ELP transfer failed");
+            self.pay_collaterals(caller, collateral_amounts);
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), SettlementRedeemed {
+                user: caller,
+                elc_amount,
+                relp_amount: 0,
+                elp_amount,
+            });
+            Ok(elp_amount)
+        }
+
+        /// Burn `relp_amount` rELP of the caller for its share of what is left after ELC holders,
+        /// the ELP and each collateral. Returns the ELP paid.
+        #[ink(message)]
+        pub fn redeem_relp_settlement(&mut self, relp_amount: Balance) -> Result<Balance> {
+            let mut settlement = self.settlement.clone().ok_or(Error::NotShutdown)?;
+            let elp_amount = settlement.redeem_relp(relp_amount)?;
+            let collateral_amounts = self.take_collateral_pots(|pot| {
+                take_pro_rata(relp_amount, settlement.relp_supply, pot.relp_pot, &mut pot.relp_paid)
+            })?;
+            let caller = self.env().caller();
+            if self.relp_contract.burn(caller, relp_amount).is_err() {
+                return Err(Error::BurnFailed)
+            }
+            self.settlement = Some(settlement);
+            // rELP已销毁，转账失败时panic回滚整个调用
+            assert!(self.env().transfer(caller, elp_amount).is_ok(), "ELP transfer failed");
+            self.pay_collaterals(caller, collateral_amounts);
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), SettlementRedeemed {
+                user: caller,
+                elc_amount: 0,
+                relp_amount,
+                elp_amount,
+            });
+            Ok(elp_amount)
+        }
+
+        fn unwind_pol(&mut self, shares: Balance) -> Result<(Balance, Balance)> {
+            if shares == 0 || shares > self.pol_shares {
+                return Err(Error::InsufficientShares)
+            }
+            let (elp_amount, elc_amount) = withdraw_pool(&mut self.exchange(), shares)?;
+            // 份额已取出，销毁失败时panic回滚整个调用
+            assert!(self.elc_contract.burn(self.env().account_id(), elc_amount).is_ok(), "ELC burn failed");
+            let (elp_deployed, elc_deployed) = pol_cost(shares, self.pol_shares, self.pol_elp, self.pol_elc);
+            self.pol_elp -= elp_deployed;
+            self.pol_elc -= elc_deployed;
+            self.pol_shares -= shares;
+            self.risk_reserve += elp_amount;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), PolWithdrawn {
+                elp_amount,
+                elc_amount,
+                shares,
+            });
+            Ok((elp_amount, elc_amount))
+        }
+
+        /// Take the payout of every collateral pot with `take`, the pots are only updated when
+        /// all payouts succeed. Returns the non-zero `(token, amount)` payouts.
+        fn take_collateral_pots<F>(&mut self, mut take: F) -> Result<Vec<(AccountId, Balance)>>
+        where
+            F: FnMut(&mut CollateralPot) -> Result<Balance>,
+        {
+            let mut updated = Vec::new();
+            for (token, pot) in self.collateral_pots.iter() {
+                let mut pot = pot.clone();
+                let amount = take(&mut pot)?;
+                if amount > 0 {
+                    updated.push((*token, pot, amount));
+                }
+            }
+            Ok(updated.into_iter().map(|(token, pot, amount)| {
+                self.collateral_pots.insert(token, pot);
+                (token, amount)
+            }).collect())
+        }
+
+        /// Transfer the collateral payouts of a settlement redemption to `to`. Panics on
+        /// failure, reverting the burn before it.
+        fn pay_collaterals(&mut self, to: AccountId, amounts: Vec<(AccountId, Balance)>) {
+            for (token, amount) in amounts {
+                let mut token_contract = PSP22Ref::new(token);
+                assert!(token_contract.transfer(to, amount, Vec::new()).is_ok(), "Collateral transfer failed");
+                ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), SettlementCollateralPaid {
+                    user: to,
+                    token,
+                    amount,
+                });
+            }
+        }
+
+        fn settle_auction(&mut self, auction: Auction) {
+            self.auction = None;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), RecapAuctionSettled {
+                raised: auction.raised,
+                relp_sold: auction.relp_sold,
+            });
+        }
+
+        fn check_live(&self) -> Result<()> {
+            if self.settlement.is_some() {
+                return Err(Error::Shutdown)
+            }
+            Ok(())
+        }
+
+        /// Returns `(rate, cap)` of the keeper bounty, rate base 10000.
+        #[ink(message)]
+        pub fn keeper_bounty(&self) -> (u128, Balance) {
+            (self.keeper_bounty_rate, self.keeper_bounty_cap)
+        }
+
+        /// Only callable by govern contract.
+        #[ink(message)]
+        pub fn set_keeper_bounty(&mut self, rate: u128, cap: Balance) -> Result<()> {
+            self.only_govern()?;
+            if rate > MAX_KEEPER_BOUNTY_RATE {
+                return Err(Error::InvalidBountyRate)
+            }
+            self.keeper_bounty_rate = rate;
+            self.keeper_bounty_cap = cap;
+            Ok(())
+        }
+
+        /// ELP worth of `amount` of collateral `token` after the haircut.
+        fn collateral_in_elp(
+            &self,
+            token: AccountId,
+            collateral: &Collateral,
+            amount: Balance,
+            elp_price: u128,
+        ) -> Result<Balance> {
+            let oracle: OracleRef = FromAccountId::from_account_id(collateral.oracle);
+            let price = check_price(oracle.asset_price(token))?;
+            Ok(haircut_value(amount, price, collateral.haircut, elp_price))
+        }
+
+        /// ELP worth of all collateral reserves, empty collaterals are skipped.
+        fn collateral_value(&self) -> Result<Balance> {
+            let (_, elp_price) = self.prices()?;
+            let mut value = 0;
+            for (token, collateral) in self.collaterals.iter() {
+                if collateral.reserve > 0 {
+                    // 抵押品预言机无报价时按0计价，不阻塞ELP的流程
+                    value += self.collateral_in_elp(*token, collateral, collateral.reserve, elp_price).unwrap_or(0);
+                }
+            }
+            Ok(value)
+        }
+
+        fn only_govern(&self) -> Result<()> {
+            if self.env().caller() != self.govern_accountid {
+                return Err(Error::OnlyGovernAccess)
+            }
+            Ok(())
+        }
+
+        fn keeper_bounty_of(&self, elp_traded: u128) -> u128 {
+            (elp_traded * self.keeper_bounty_rate / 10000).min(self.keeper_bounty_cap)
+        }
+
+        /// Pay the caller its bounty for `elp_traded` ELP of adjustment from risk reserve.
+        /// Returns the bounty paid, 0 if the transfer fails.
+        fn pay_keeper(&mut self, elp_traded: u128) -> Balance {
+            let bounty = self.keeper_bounty_of(elp_traded).min(self.risk_reserve);
+            if bounty == 0 {
+                return 0
+            }
+            let keeper = self.env().caller();
+            if self.env().transfer(keeper, bounty).is_err() {
+                // 调整已完成，转账失败时不支付奖励
+                return 0
+            }
+            self.risk_reserve -= bounty;
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), KeeperRewarded {
+                keeper,
+                elp_traded,
+                bounty,
+            });
+            bounty
+        }
+
+        /// Returns whether `(liability ratio, ELC price, adjust gap)` allow `expand_elc`.
+        fn expand_checks(&self) -> Result<(bool, bool, bool)> {
+            let lr = self.liability_ratio()?;
+            let (elc_price, _) = self.prices()?;
+            let elcaim_deviation = self.govern_contract.current_elcaim(); //theory deviation is [elcaim * 98, elcaim]
+            Ok((lr < self.params.expand_ratio, elc_price > elcaim_deviation, self.gap_elapsed(self.last_expand_time)))
+        }
+
+        /// Returns whether `(ELC price, adjust gap)` allow `contract_elc`.
+        fn contract_checks(&self) -> Result<(bool, bool)> {
+            let (elc_price, _) = self.prices()?;
+            let elcaim_deviation = self.govern_contract.current_elcaim(); //theory deviation is [elcaim * 98, elcaim]
+            let band = elcaim_deviation * self.params.contract_band / 100;
+            Ok((elc_price < band, self.gap_elapsed(self.last_contract_time)))
+        }
+
+        fn gap_elapsed(&self, last_time: u128) -> bool {
+            let block_time: u128 = self.env().block_timestamp().into();
+            block_time.saturating_sub(last_time) >= self.params.adjust_gap
+        }
+
+        /// Returns the ELC sold from reserves by `expand_elc` and the ELC issued after
+        /// the reserves run out, `None` when they are enough.
+        fn plan_expand(&self) -> Result<(u128, Option<u128>)> {
+            let (elc_price, _) = self.prices()?;
+            let elcaim_deviation = self.govern_contract.current_elcaim();
+            // 按交易池储备计算兑换量
+            let (reserve_elp, reserve_elc) = self.exchange().reserves();
+            let delta_elc = expand_size(reserve_elp, reserve_elc)?;
+
+            let elc_reserve_all = self.elc_risk_reserve_source + self.elc_reserve_source;
+            if elc_reserve_all >= delta_elc {
+                return Ok((delta_elc, None))
+            }
+            let elc_balance: u128 = self.elc_contract.total_supply();
+            // 理论增发量
+            let mut issue = elc_price.saturating_sub(elcaim_deviation) * elc_balance / elcaim_deviation;
+            // 将所有储备ELC和风险准备ELC均换成ELP后，剩下还需要兑换的量
+            // 根据需求: 理论增发量的5% == delta_elc - elc_reserve_all, 即剩余增发量=(result of sub) * 20.
+            let remain_elc = (delta_elc - elc_reserve_all) * 100 / (100 - self.params.reward_share);
+            // elc reserve is zero, do elc additional issue work.
+            if elc_reserve_all > 0 && issue > remain_elc {
+                issue = remain_elc;
+            }
+            Ok((elc_reserve_all, Some(issue)))
+        }
+
+        /// Returns the ELP sold by `contract_elc` into a pool of `reserve_elp` ELP and whether it
+        /// comes from the `risk_reserve`.
+        fn plan_contract(&self, reserve_elp: u128, risk_reserve: u128) -> Result<(u128, bool)> {
+            let (elc_price, _) = self.prices()?;
+            let elcaim_deviation = self.govern_contract.current_elcaim();
+            // 计算兑换量，使ELC价格回到ELCaim * 98%
+            let band = elcaim_deviation * self.params.contract_band / 100;
+            let delta_elp = contract_size(reserve_elp, elc_price, band)?;
+            if risk_reserve > 0 {
+                Ok((delta_elp.min(risk_reserve), true))
+            } else {
+                Ok((delta_elp.min(self.reserve * self.params.reserve_draw_cap / 100), false))
+            }
+        }
+
+        fn apply_changes(&mut self, changes: &ReserveChanges) {
+            self.reserve = shift(self.reserve, changes.reserve);
+            self.risk_reserve = shift(self.risk_reserve, changes.risk_reserve);
+            self.elc_reserve_source = shift(self.elc_reserve_source, changes.elc_reserve);
+            self.elc_risk_reserve_source = shift(self.elc_risk_reserve_source, changes.elc_risk_reserve);
+        }
+
+        fn swap_elp_with_elc(
             &mut self, 
             elc_amount: u128, 
             elc_reserve: u128, 
             elc_risk: u128,
             elp_amount: u128) {
-            // 初始化为0
-            let (mut elc_event_reserve, mut elc_event_risk, mut elc_event_amount) = (0, 0, 0);
-
-            if elc_reserve >= elc_amount {
-                elc_event_reserve = elc_amount;
-                self.elc_reserve_source -= elc_event_reserve;
-                self.reserve += elp_amount;
-            } else {
-                // update elc reserve and elc risk reserve
-                if elc_risk > 0 || elc_reserve > 0 {
-                    elc_event_reserve = elc_reserve;
-                    elc_event_risk = elc_amount - elc_reserve;
-                    self.elc_reserve_source -= elc_event_reserve;
-                    self.elc_risk_reserve_source -= elc_event_risk;
-                } else {
-                    elc_event_amount = elc_amount;
-                }
-                
-                // update elp reserve and elc risk reserve
-                let delta_reserve_elp = elc_reserve * elp_amount / elc_amount;
-                self.reserve += delta_reserve_elp;
-                self.risk_reserve += elp_amount - delta_reserve_elp;
-            }
+            let changes = expand_changes(elc_amount, elc_reserve, elc_risk, elp_amount);
+            self.apply_changes(&changes);
+            let (elc_event_reserve, elc_event_risk) =
+                ((-changes.elc_reserve) as u128, (-changes.elc_risk_reserve) as u128);
 
             ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ExpandEvent {
             // self.env().emit_event(ExpandEvent {
                 elc_reserve_amount: elc_event_reserve,
                 elc_risk_amount: elc_event_risk,
-                elc_raise_amount: elc_event_amount,
+                elc_raise_amount: elc_amount - elc_event_reserve - elc_event_risk,
                 elp_amount: elp_amount,
             });
         }
 
         fn swap_elc_with_elp(&mut self, elp_risk: u128, elp_reserve: u128, elc_amount: u128) {
-            if elp_reserve > 0{
-                self.elc_reserve_source += elc_amount;
-                self.reserve -= elp_reserve;
-                ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ContractEvent {
-                // self.env().emit_event(ContractEvent {
-                    elc_risk_reserve_source: 0,
-                    elc_reserve_source: elc_amount,
-                    risk_reserve_consumed: 0,
-                    reserve_consumed: elp_reserve,
-                });
-            } else {
-                self.elc_risk_reserve_source += elc_amount;
-                self.risk_reserve -= elp_risk;
-                ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ContractEvent {
-                // self.env().emit_event(ContractEvent {
-                    elc_risk_reserve_source: elc_amount,
-                    elc_reserve_source: 0,
-                    risk_reserve_consumed: elp_risk,
-                    reserve_consumed: 0,
-                });
+            let changes = contract_changes(elp_risk, elp_reserve, elc_amount);
+            self.apply_changes(&changes);
+            ::ink_lang::codegen::EmitEvent::<Stable>::emit_event(Self::env(), ContractEvent {
+            // self.env().emit_event(ContractEvent {
+                elc_risk_reserve_source: changes.elc_risk_reserve as u128,
+                elc_reserve_source: changes.elc_reserve as u128,
+                risk_reserve_consumed: (-changes.risk_reserve) as u128,
+                reserve_consumed: (-changes.reserve) as u128,
+            });
+        }
+    }
+
+    /// ELC sold by one expansion: ELC worth `delta_elp = reserve_elp / 999` at the pool price,
+    /// which is 0.1% price impact, `delta_elp = 0.1% * elp / (1 - 0.1%)`.
+    fn expand_size(reserve_elp: u128, reserve_elc: u128) -> Result<u128> {
+        if reserve_elp == 0 || reserve_elc == 0 {
+            return Err(Error::InsufficientLiquidity)
+        }
+        let delta_elp = reserve_elp / 999;
+        Ok(delta_elp * reserve_elc / reserve_elp)
+    }
+
+    /// ELP sold by one contraction to lift ELC price from `elc_price` to `target_price`:
+    /// `delta_elp = impact * elp / (1 - impact)`, impact base 10000 and capped by
+    /// `MAX_PRICE_IMPACT`.
+    fn contract_size(reserve_elp: u128, elc_price: u128, target_price: u128) -> Result<u128> {
+        if reserve_elp == 0 {
+            return Err(Error::InsufficientLiquidity)
+        }
+        // 将价格冲击放大10000倍，用于防止计算出现小数
+        let impact = (target_price.saturating_sub(elc_price) * 10000 / elc_price).min(MAX_PRICE_IMPACT);
+        Ok(reserve_elp * impact / (10000 - impact))
+    }
+
+    /// Sell `elc_amount` ELC through `exchange` for at least `min_elp`, returns the ELP got.
+    fn sell_elc<S: SwapAdapter>(exchange: &mut S, elc_amount: u128, min_elp: u128) -> Result<u128>
+    where
+        Error: From<S::Error>,
+    {
+        Ok(exchange.swap_elc_to_elp(elc_amount, min_elp)?)
+    }
+
+    /// Sell `elp_amount` ELP through `exchange` for at least `min_elc`, returns the ELC got.
+    fn sell_elp<S: SwapAdapter>(exchange: &mut S, elp_amount: u128, min_elc: u128) -> Result<u128>
+    where
+        Error: From<S::Error>,
+    {
+        Ok(exchange.swap_elp_to_elc(elp_amount, min_elc)?)
+    }
+
+    /// Add `elp_amount` ELP and approved `elc_amount` ELC to the pool, returns the shares.
+    fn provide_liquidity<S: SwapAdapter>(exchange: &mut S, elp_amount: u128, elc_amount: u128) -> Result<u128>
+    where
+        Error: From<S::Error>,
+    {
+        Ok(exchange.add_liquidity(elp_amount, elc_amount)?)
+    }
+
+    /// Burn `shares` of the pool, returns the `(elp, elc)` got.
+    fn withdraw_pool<S: SwapAdapter>(exchange: &mut S, shares: u128) -> Result<(u128, u128)>
+    where
+        Error: From<S::Error>,
+    {
+        Ok(exchange.remove_liquidity(shares)?)
+    }
+
+    /// ELP and ELC deployed for `shares` of the `pol_shares` held, the rounding dust goes
+    /// with the last shares.
+    fn pol_cost(shares: u128, pol_shares: u128, pol_elp: u128, pol_elc: u128) -> (u128, u128) {
+        if shares == pol_shares {
+            return (pol_elp, pol_elc)
+        }
+        (share_of(shares, pol_shares, pol_elp), share_of(shares, pol_shares, pol_elc))
+    }
+
+    /// Reserve changes of selling `elc_amount` ELC for `elp_amount` ELP, the ELC is taken
+    /// from the ELC reserve first, then from the ELC risk reserve, the rest is newly issued.
+    fn expand_changes(elc_amount: u128, elc_reserve: u128, elc_risk: u128, elp_amount: u128) -> ReserveChanges {
+        if elc_reserve >= elc_amount {
+            return ReserveChanges {
+                reserve: elp_amount as i128,
+                elc_reserve: -(elc_amount as i128),
+                ..Default::default()
             }
         }
+        // update elc reserve and elc risk reserve
+        let elc_risk_used = if elc_risk > 0 || elc_reserve > 0 { elc_amount - elc_reserve } else { 0 };
+        // update elp reserve and elc risk reserve
+        let delta_reserve_elp = elc_reserve * elp_amount / elc_amount;
+        ReserveChanges {
+            reserve: delta_reserve_elp as i128,
+            risk_reserve: (elp_amount - delta_reserve_elp) as i128,
+            elc_reserve: -(elc_reserve as i128),
+            elc_risk_reserve: -(elc_risk_used as i128),
+        }
+    }
 
-        // TODO: 此处扩大了1e12次方，以防止浮点数出现，后续需要除去1e12
-        // 考虑将(elc_amount, cur_total_coinday, timestamp)三元素元组替换(per_coinday, timestamp)
-        // 将除法运算置后到最终结果
-        /*
-        fn update_total_coinday(&mut self, elc_amount: u128, now_time: u128) -> (u128, u128) {
-            let (cur_total_coinday, last_time) = self.add_contract.total_coinday();
-            let total_supply = self.relp_contract.total_supply();
-            let increase_coinday = total_supply * (now_time - last_time);
-            let new_total_coinday = cur_total_coinday + increase_coinday;
-            // update total coinday
-            assert!(self.add_contract.update_total_coinday((new_total_coinday, now_time)).is_ok());
-            let per_coinday = elc_amount * 1e12 as u128 / new_total_coinday;
-            (per_coinday, now_time)
+    /// Reserve changes of selling `elp_risk` ELP of risk reserve or `elp_reserve` ELP of
+    /// reserve for `elc_amount` ELC.
+    fn contract_changes(elp_risk: u128, elp_reserve: u128, elc_amount: u128) -> ReserveChanges {
+        if elp_reserve > 0 {
+            ReserveChanges {
+                reserve: -(elp_reserve as i128),
+                elc_reserve: elc_amount as i128,
+                ..Default::default()
+            }
+        } else {
+            ReserveChanges {
+                risk_reserve: -(elp_risk as i128),
+                elc_risk_reserve: elc_amount as i128,
+                ..Default::default()
+            }
         }
-        */
-        // 使用三元组见上述描述, 只需要返回新的总币天
-        fn update_total_coinday(&mut self, now_time: u128) -> u128 {
-            let (cur_total_coinday, last_time) = self.add_contract.total_coinday();
-            let total_supply = self.relp_contract.total_supply();
-            let increase_coinday = total_supply * (now_time - last_time);
-            let new_total_coinday = cur_total_coinday + increase_coinday;
-            // update total coinday
-            assert!(self.add_contract.update_total_coinday((new_total_coinday, now_time)).is_ok());
-            new_total_coinday
+    }
+
+    /// Split `elp_total` ELP into `(elc_pot, relp_pot)`: ELC holders are owed the value of
+    /// `elc_supply` at the given prices, paid in full when the reserves cover it.
+    fn settlement_pots(elp_total: u128, elc_supply: u128, elc_price: u128, elp_price: u128) -> (u128, u128) {
+        let elc_pot = (elc_supply * elc_price / elp_price).min(elp_total);
+        (elc_pot, elp_total - elc_pot)
+    }
+
+    /// Split `amount` of an asset into the parts of ELC and rELP holders, ELC holders get
+    /// `elc_value` of `total_value`, or everything when nothing is valued.
+    fn split_pot(amount: u128, elc_value: u128, total_value: u128) -> Result<(u128, u128)> {
+        if total_value == 0 {
+            return Ok((amount, 0))
+        }
+        let elc_part = mul_div(amount, elc_value, total_value)?;
+        Ok((elc_part, amount - elc_part))
+    }
+
+    /// Part of `pot` owed to `amount` of `supply`, added to `paid`. Fails when the pot would
+    /// be overpaid.
+    fn take_pro_rata(amount: u128, supply: u128, pot: u128, paid: &mut u128) -> Result<u128> {
+        let share = pro_rata(amount, supply, pot);
+        if *paid + share > pot {
+            return Err(Error::SettlementExhausted)
+        }
+        *paid += share;
+        Ok(share)
+    }
+
+    /// Liability ratio above `remove_ratio` and risk reserve below 1% of reserve.
+    fn needs_recap(lr: u128, remove_ratio: u128, risk_reserve: u128, reserve: u128) -> bool {
+        lr > remove_ratio && risk_reserve * 100 < reserve
+    }
+
+    /// Auction price at `now`, falling linearly to the floor at the end of the duration.
+    fn auction_price(auction: &Auction, now: u128) -> u128 {
+        let elapsed = now.saturating_sub(auction.start_time).min(auction.duration);
+        auction.start_price - (auction.start_price - auction.floor_price) * elapsed / auction.duration
+    }
+
+    /// Part of `pot` owed to `amount` of `supply`.
+    fn pro_rata(amount: u128, supply: u128, pot: u128) -> u128 {
+        if supply == 0 {
+            return 0
+        }
+        amount * pot / supply
+    }
+
+    /// ELP worth of `amount` of an asset at `price`, less `haircut` percent.
+    fn haircut_value(amount: u128, price: u128, haircut: u128, elp_price: u128) -> u128 {
+        amount * price * (100 - haircut) / 100 / elp_price
+    }
+
+    /// Fee of `amount` at `rate`, base 10000.
+    fn fee_of(amount: u128, rate: u128) -> u128 {
+        amount * rate / 10000
+    }
+
+    /// ELP paid for `elc_amount` ELC at `elcaim` and the fee kept of it, `fee_rate` base 10000.
+    fn redeem_value(elc_amount: u128, elcaim: u128, elp_price: u128, fee_rate: u128) -> (u128, u128) {
+        let value = elc_amount * elcaim / elp_price;
+        let fee = value * fee_rate / 10000;
+        (value - fee, fee)
+    }
+
+    /// Split issued ELC into `(reward_elc, swap_elc)`, `reward_share` base 100.
+    fn split_issue(issue: u128, reward_share: u128) -> (u128, u128) {
+        let reward_elc = issue * reward_share / 100;
+        (reward_elc, issue - reward_elc)
+    }
+
+    fn shift(value: u128, change: i128) -> u128 {
+        if change >= 0 {
+            value + change as u128
+        } else {
+            value.saturating_sub(change.unsigned_abs())
+        }
+    }
+
+    /// Liability ratio of `elc_supply` ELC backed by `reserve` ELP, base 100, within [1, 100].
+    fn ratio(elc_supply: u128, reserve: u128, elc_price: u128, elp_price: u128) -> u128 {
+        // 没有储备时，无负债视为最低负债率
+        if reserve == 0 {
+            return if elc_supply == 0 { 1 } else { 100 }
+        }
+        let lr = elc_supply * elc_price * 100 / (elp_price * reserve); //100 as base
+        lr.max(1).min(100)
+    }
+
+    fn check_deadline(now: u128, deadline: u128) -> Result<()> {
+        if now > deadline {
+            return Err(Error::DeadlineExpired)
         }
+        Ok(())
+    }
+
+    fn check_min_out(amount: Balance, min_out: Balance) -> Result<()> {
+        if amount < min_out {
+            return Err(Error::SlippageExceeded)
+        }
+        Ok(())
+    }
+
+    fn check_price(price: u128) -> Result<u128> {
+        if price == 0 {
+            return Err(Error::PriceUnavailable)
+        }
+        Ok(price)
     }
 
     /// Unit tests
@@ -501,22 +2256,455 @@ mod stable {
         /// Imports all the definitions from outer scope so we can use them here.
         use super::*;
         use ink_lang as ink;
+        use swap::shares_for;
 
-        #[ink::test]
-        fn new_works() {
-            let stable = Stable::new(
+        /// Constant-product pool kept in memory, stands for the exchange contract.
+        pub struct MockSwap {
+            pub elp_reserve: u128,
+            pub elc_reserve: u128,
+            pub shares: u128,
+        }
+
+        impl SwapAdapter for MockSwap {
+            type Error = SwapError;
+
+            fn quote_elc_to_elp(&self, elc_in: u128) -> u128 {
+                input_price(elc_in, self.elc_reserve, self.elp_reserve)
+            }
+
+            fn quote_elp_to_elc(&self, elp_in: u128) -> u128 {
+                input_price(elp_in, self.elp_reserve, self.elc_reserve)
+            }
+
+            fn swap_elc_to_elp(&mut self, elc_in: u128, min_out: u128) -> core::result::Result<u128, SwapError> {
+                let elp_out = self.quote_elc_to_elp(elc_in);
+                if elp_out == 0 {
+                    return Err(SwapError::InsufficientLiquidity)
+                }
+                if elp_out < min_out {
+                    return Err(SwapError::SlippageExceeded)
+                }
+                self.elc_reserve += elc_in;
+                self.elp_reserve -= elp_out;
+                Ok(elp_out)
+            }
+
+            fn swap_elp_to_elc(&mut self, elp_in: u128, min_out: u128) -> core::result::Result<u128, SwapError> {
+                let elc_out = self.quote_elp_to_elc(elp_in);
+                if elc_out == 0 {
+                    return Err(SwapError::InsufficientLiquidity)
+                }
+                if elc_out < min_out {
+                    return Err(SwapError::SlippageExceeded)
+                }
+                self.elp_reserve += elp_in;
+                self.elc_reserve -= elc_out;
+                Ok(elc_out)
+            }
+
+            fn reserves(&self) -> (u128, u128) {
+                (self.elp_reserve, self.elc_reserve)
+            }
+
+            fn add_liquidity(&mut self, elp_amount: u128, elc_amount: u128) -> core::result::Result<u128, SwapError> {
+                let shares = shares_for(elp_amount, elc_amount, self.elp_reserve, self.elc_reserve, self.shares);
+                if shares == 0 {
+                    return Err(SwapError::InvalidAmount)
+                }
+                self.elp_reserve += elp_amount;
+                self.elc_reserve += elc_amount;
+                self.shares += shares;
+                Ok(shares)
+            }
+
+            fn remove_liquidity(&mut self, shares: u128) -> core::result::Result<(u128, u128), SwapError> {
+                if shares == 0 || shares > self.shares {
+                    return Err(SwapError::InsufficientShares)
+                }
+                let elp_out = share_of(shares, self.shares, self.elp_reserve);
+                let elc_out = share_of(shares, self.shares, self.elc_reserve);
+                self.elp_reserve -= elp_out;
+                self.elc_reserve -= elc_out;
+                self.shares -= shares;
+                Ok((elp_out, elc_out))
+            }
+
+            fn total_shares(&self) -> u128 {
+                self.shares
+            }
+        }
+
+        fn new_stable() -> Stable {
+            Stable::new(
                 AccountId::from([0x1; 32]),
                 AccountId::from([0x2; 32]),
                 AccountId::from([0x3; 32]),
                 AccountId::from([0x4; 32]),
                 AccountId::from([0x5; 32]),
                 AccountId::from([0x6; 32]),
-            );
+            )
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let stable = new_stable();
             assert_eq!(stable.elp_reserve(), 0);
             assert_eq!(stable.elp_risk_reserve(), 0);
             assert_eq!(stable.elc_reserve(), 0);
             assert_eq!(stable.elc_risk_reserve(), 0);
-            assert_eq!(stable.adjust_gap, 3600*24*1000);
+            assert_eq!(stable.parameters().adjust_gap, 3600*24*1000);
+        }
+
+        #[ink::test]
+        fn parameters_is_valid_works() {
+            let params = Parameters::default();
+            assert!(params.is_valid());
+            assert!(!Parameters { issue_ratio: 70, ..params.clone() }.is_valid());
+            assert!(!Parameters { remove_ratio: 100, ..params.clone() }.is_valid());
+            assert!(!Parameters { reward_share: 100, ..params.clone() }.is_valid());
+            assert!(!Parameters { contract_band: 80, ..params.clone() }.is_valid());
+            assert!(!Parameters { reserve_draw_cap: 0, ..params.clone() }.is_valid());
+            assert!(!Parameters { redeem_fee: MAX_REDEEM_FEE + 1, ..params.clone() }.is_valid());
+            assert!(!Parameters { redeem_cap: 0, ..params.clone() }.is_valid());
+            assert!(!Parameters { mint_fee: FeeSchedule::Flat(MAX_LIQUIDITY_FEE + 1), ..params.clone() }.is_valid());
+            assert!(!Parameters { pol_share: 101, ..params.clone() }.is_valid());
+            assert!(!Parameters { adjust_gap: 0, ..params }.is_valid());
+        }
+
+        #[ink::test]
+        fn set_parameters_works() {
+            let mut stable = new_stable();
+            let params = Parameters { expand_ratio: 60, ..Parameters::default() };
+            assert_eq!(stable.set_parameters(params.clone()), Err(Error::OnlyGovernAccess));
+
+            // let the default caller act as govern.
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts.");
+            stable.govern_accountid = accounts.alice;
+            assert!(stable.set_parameters(params.clone()).is_ok());
+            assert_eq!(stable.parameters(), params);
+            assert_eq!(
+                stable.set_parameters(Parameters { issue_ratio: 0, ..params }),
+                Err(Error::InvalidParameters));
+        }
+
+        #[ink::test]
+        fn set_collateral_works() {
+            let mut stable = new_stable();
+            let token = AccountId::from([0x7; 32]);
+            let oracle = AccountId::from([0x4; 32]);
+            assert_eq!(stable.set_collateral(token, oracle, 20, 1000), Err(Error::OnlyGovernAccess));
+
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts.");
+            stable.govern_accountid = accounts.alice;
+            assert_eq!(stable.set_collateral(token, oracle, 100, 1000), Err(Error::InvalidParameters));
+            assert!(stable.set_collateral(token, oracle, 20, 1000).is_ok());
+            assert_eq!(stable.collateral_tokens(), vec![token]);
+
+            // reserves are kept when the collateral is updated.
+            stable.collaterals.get_mut(&token).unwrap().reserve = 300;
+            assert!(stable.set_collateral(token, oracle, 30, 0).is_ok());
+            assert_eq!(stable.collateral(token), Some(Collateral {
+                oracle,
+                haircut: 30,
+                debt_ceiling: 0,
+                reserve: 300,
+                risk_reserve: 0,
+            }));
+            assert_eq!(
+                stable.add_liquidity_with(AccountId::from([0x8; 32]), 100, 0, 0, u128::MAX),
+                Err(Error::UnknownCollateral));
+        }
+
+        #[ink::test]
+        fn settlement_pots_works() {
+            // 100 ELC worth 50 ELP, reserves cover it.
+            assert_eq!(settlement_pots(80, 100, 1, 2), (50, 30));
+            // undercollateralized, ELC holders take all.
+            assert_eq!(settlement_pots(40, 100, 1, 2), (40, 0));
+            assert_eq!(pro_rata(25, 100, 40), 10);
+            assert_eq!(pro_rata(25, 0, 40), 0);
+            // collateral is split in the proportion of the ELC value to the basket.
+            let (elc_value, _) = settlement_pots(80 + 40, 100, 1, 2);
+            assert_eq!(split_pot(80, elc_value, 120), Ok((33, 47)));
+            assert_eq!(split_pot(300, elc_value, 120), Ok((125, 175)));
+            assert_eq!(split_pot(300, 0, 0), Ok((300, 0)));
+        }
+
+        #[ink::test]
+        fn settlement_redemptions_work() {
+            let mut settlement = Settlement {
+                time: 0,
+                elc_price: 1,
+                elp_price: 2,
+                elc_supply: 100,
+                relp_supply: 300,
+                elc_pot: 50,
+                relp_pot: 30,
+                elc_paid: 0,
+                relp_paid: 0,
+                pol_shares: 0,
+            };
+            assert_eq!(settlement.redeem_elc(40), Ok(20));
+            assert_eq!(settlement.redeem_elc(60), Ok(30));
+            assert_eq!(settlement.elc_paid, settlement.elc_pot);
+            // nothing left for ELC minted after shutdown.
+            assert_eq!(settlement.redeem_elc(2), Err(Error::SettlementExhausted));
+            assert_eq!(settlement.elc_paid, 50);
+
+            assert_eq!(settlement.redeem_relp(150), Ok(15));
+            assert_eq!(settlement.redeem_relp(100), Ok(10));
+            assert_eq!(settlement.redeem_relp(100), Err(Error::SettlementExhausted));
+            assert_eq!(settlement.redeem_relp(50), Ok(5));
+            assert_eq!((settlement.relp_paid, settlement.relp_pot), (30, 30));
+        }
+
+        #[ink::test]
+        fn shutdown_works() {
+            let mut stable = new_stable();
+            assert_eq!(stable.shutdown(), Err(Error::OnlyGuardianAccess));
+            assert_eq!(stable.redeem_elc_settlement(10), Err(Error::NotShutdown));
+            assert_eq!(stable.set_guardian(None), Err(Error::OnlyGovernAccess));
+
+            stable.settlement = Some(Settlement {
+                time: 0,
+                elc_price: 1,
+                elp_price: 2,
+                elc_supply: 100,
+                relp_supply: 100,
+                elc_pot: 50,
+                relp_pot: 30,
+                elc_paid: 0,
+                relp_paid: 0,
+                pol_shares: 0,
+            });
+            assert_eq!(stable.expand_elc(), Err(Error::Shutdown));
+            assert_eq!(stable.contract_elc(), Err(Error::Shutdown));
+            assert_eq!(stable.remove_liquidity(10, 10, 0, u128::MAX), Err(Error::Shutdown));
+        }
+
+        #[ink::test]
+        fn haircut_value_works() {
+            // 100 tokens at price 3 with 20% haircut are worth 120 ELP at price 2.
+            assert_eq!(haircut_value(100, 3, 20, 2), 120);
+            assert_eq!(haircut_value(100, 3, 0, 2), 150);
+        }
+
+        #[ink::test]
+        fn fee_schedule_works() {
+            assert_eq!(FeeSchedule::Flat(30).rate(80), 30);
+            let rising = FeeSchedule::Dynamic { low: 10, high: 110 };
+            assert_eq!(rising.rate(0), 10);
+            assert_eq!(rising.rate(50), 60);
+            assert_eq!(rising.rate(150), 110);
+            let falling = FeeSchedule::Dynamic { low: 110, high: 10 };
+            assert_eq!(falling.rate(50), 60);
+            assert!(!FeeSchedule::Dynamic { low: 0, high: MAX_LIQUIDITY_FEE + 1 }.is_valid());
+            assert_eq!(fee_of(20000, 30), 60);
+        }
+
+        #[ink::test]
+        fn redeem_value_works() {
+            // 1000 ELC at ELCaim 2 is worth 500 ELP at price 4, 0.5% fee.
+            assert_eq!(redeem_value(1000, 2, 4, 50), (498, 2));
+            assert_eq!(redeem_value(1000, 2, 4, 0), (500, 0));
+        }
+
+        #[ink::test]
+        fn split_issue_works() {
+            assert_eq!(split_issue(1000, 95), (950, 50));
+            assert_eq!(split_issue(999, 95), (949, 50));
+        }
+
+        #[ink::test]
+        fn keeper_bounty_works() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts.");
+            let mut stable = new_stable();
+            stable.govern_accountid = accounts.alice;
+            assert_eq!(stable.keeper_bounty(), (0, 0));
+            assert_eq!(stable.keeper_bounty_of(10000), 0);
+
+            // alice deploys govern here.
+            assert!(stable.set_keeper_bounty(10, 5).is_ok());
+            assert_eq!(stable.keeper_bounty_of(4000), 4);
+            assert_eq!(stable.keeper_bounty_of(10000), 5);
+            assert_eq!(stable.set_keeper_bounty(101, 5), Err(Error::InvalidBountyRate));
+
+            stable.govern_accountid = accounts.bob;
+            assert_eq!(stable.set_keeper_bounty(10, 5), Err(Error::OnlyGovernAccess));
+        }
+
+        #[ink::test]
+        fn sell_through_adapter_works() {
+            let mut exchange = MockSwap { elp_reserve: 1000, elc_reserve: 4000, shares: 0 };
+            assert_eq!(sell_elc(&mut exchange, 400, min_out(90, SWAP_TOLERANCE)), Ok(90));
+            assert_eq!(exchange.reserves(), (910, 4400));
+            assert_eq!(sell_elp(&mut exchange, 90, min_out(394, SWAP_TOLERANCE)), Ok(394));
+
+            let mut empty = MockSwap { elp_reserve: 0, elc_reserve: 0, shares: 0 };
+            assert_eq!(sell_elc(&mut empty, 400, 0), Err(Error::SwapFailed));
+            assert_eq!(sell_elp(&mut empty, 100, 0), Err(Error::SwapFailed));
+        }
+
+        #[ink::test]
+        fn sell_failed_below_min_out() {
+            let mut exchange = MockSwap { elp_reserve: 1000, elc_reserve: 4000, shares: 0 };
+            assert_eq!(sell_elc(&mut exchange, 400, 91), Err(Error::SlippageExceeded));
+            assert_eq!(sell_elp(&mut exchange, 90, 400), Err(Error::SlippageExceeded));
+            assert_eq!(exchange.reserves(), (1000, 4000));
+        }
+
+        #[ink::test]
+        fn swap_call_errors_work() {
+            assert_eq!(Error::from(CallError::Callee(SwapError::SlippageExceeded)), Error::SlippageExceeded);
+            assert_eq!(Error::from(CallError::Callee(SwapError::InsufficientLiquidity)), Error::SwapFailed);
+            assert_eq!(Error::from(CallError::<SwapError>::Env(ink_env::Error::CalleeTrapped)), Error::SwapFailed);
+        }
+
+        /// Selector of an inherent message named `name`, derived like ink! does.
+        fn selector_of(name: &[u8]) -> [u8; 4] {
+            let mut hash = [0u8; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(name, &mut hash);
+            [hash[0], hash[1], hash[2], hash[3]]
+        }
+
+        #[ink::test]
+        fn psp22_selectors_work() {
+            assert_eq!(selector_of(b"PSP22::transfer"), PSP22_TRANSFER);
+            assert_eq!(selector_of(b"PSP22::transfer_from"), PSP22_TRANSFER_FROM);
+        }
+
+        #[ink::test]
+        fn swap_selectors_work() {
+            assert_eq!(selector_of(b"quote_elc_to_elp"), swap::QUOTE_ELC_TO_ELP);
+            assert_eq!(selector_of(b"quote_elp_to_elc"), swap::QUOTE_ELP_TO_ELC);
+            assert_eq!(selector_of(b"swap_elc_to_elp"), swap::SWAP_ELC_TO_ELP);
+            assert_eq!(selector_of(b"swap_elp_to_elc"), swap::SWAP_ELP_TO_ELC);
+            assert_eq!(selector_of(b"reserves"), swap::RESERVES);
+            assert_eq!(selector_of(b"add_liquidity"), swap::ADD_LIQUIDITY);
+            assert_eq!(selector_of(b"remove_liquidity"), swap::REMOVE_LIQUIDITY);
+            assert_eq!(selector_of(b"total_shares"), swap::TOTAL_SHARES);
+        }
+
+        #[ink::test]
+        fn expand_size_works() {
+            // delta_elp = 999000 / 999 = 1000, worth 4000 ELC at pool price 1 ELC = 0.25 ELP.
+            assert_eq!(expand_size(999000, 3996000), Ok(4000));
+            assert_eq!(expand_size(0, 3996000), Err(Error::InsufficientLiquidity));
+        }
+
+        #[ink::test]
+        fn contract_size_works() {
+            // 1% impact: delta_elp = 990000 * 100 / 9900.
+            assert_eq!(contract_size(990000, 100, 101), Ok(10000));
+            // price already above target.
+            assert_eq!(contract_size(990000, 100, 98), Ok(0));
+            // impact capped at 50%: delta_elp = elp.
+            assert_eq!(contract_size(990000, 10, 98), Ok(990000));
+            assert_eq!(contract_size(0, 100, 101), Err(Error::InsufficientLiquidity));
+        }
+
+        #[ink::test]
+        fn liquidity_bounds_work() {
+            assert_eq!(check_deadline(100, 100), Ok(()));
+            assert_eq!(check_deadline(101, 100), Err(Error::DeadlineExpired));
+            assert_eq!(check_min_out(10, 10), Ok(()));
+            assert_eq!(check_min_out(9, 10), Err(Error::SlippageExceeded));
+        }
+
+        #[ink::test]
+        fn expand_changes_works() {
+            // covered by ELC reserve.
+            assert_eq!(expand_changes(100, 300, 0, 50), ReserveChanges {
+                reserve: 50,
+                elc_reserve: -100,
+                ..Default::default()
+            });
+            // 60 from ELC reserve and 40 from ELC risk reserve, ELP split the same way.
+            assert_eq!(expand_changes(100, 60, 80, 50), ReserveChanges {
+                reserve: 30,
+                risk_reserve: 20,
+                elc_reserve: -60,
+                elc_risk_reserve: -40,
+            });
+            // newly issued ELC.
+            assert_eq!(expand_changes(100, 0, 0, 50), ReserveChanges {
+                risk_reserve: 50,
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn contract_changes_works() {
+            assert_eq!(contract_changes(0, 20, 80), ReserveChanges {
+                reserve: -20,
+                elc_reserve: 80,
+                ..Default::default()
+            });
+            assert_eq!(contract_changes(20, 0, 80), ReserveChanges {
+                risk_reserve: -20,
+                elc_risk_reserve: 80,
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn ratio_works() {
+            assert_eq!(ratio(0, 0, 1, 1), 1);
+            assert_eq!(ratio(10, 0, 1, 1), 100);
+            assert_eq!(ratio(50, 100, 2, 4), 25);
+            assert_eq!(ratio(500, 100, 2, 4), 100);
+        }
+
+        #[ink::test]
+        fn auction_price_works() {
+            let auction = Auction {
+                start_time: 1000,
+                duration: 100,
+                start_price: 300000,
+                floor_price: 100000,
+                target: 5000,
+                raised: 0,
+                relp_sold: 0,
+            };
+            assert_eq!(auction_price(&auction, 0), 300000);
+            assert_eq!(auction_price(&auction, 1050), 200000);
+            assert_eq!(auction_price(&auction, 1200), 100000);
+        }
+
+        #[ink::test]
+        fn needs_recap_works() {
+            assert!(needs_recap(95, 90, 9, 1000));
+            assert!(!needs_recap(95, 90, 10, 1000));
+            assert!(!needs_recap(90, 90, 0, 1000));
+        }
+
+        #[ink::test]
+        fn bid_without_auction_fails() {
+            let mut stable = new_stable();
+            assert_eq!(stable.recap_price(), Err(Error::NoAuction));
+            assert_eq!(stable.bid_recap(0), Err(Error::NoAuction));
+            assert_eq!(stable.settle_recap_auction(), Err(Error::NoAuction));
+        }
+
+        #[ink::test]
+        fn pol_through_adapter_works() {
+            let mut exchange = MockSwap { elp_reserve: 1000, elc_reserve: 4000, shares: 1000 };
+            assert_eq!(provide_liquidity(&mut exchange, 100, 400), Ok(100));
+            assert_eq!(exchange.reserves(), (1100, 4400));
+            assert_eq!(withdraw_pool(&mut exchange, 50), Ok((50, 200)));
+            assert_eq!(withdraw_pool(&mut exchange, 2000), Err(Error::SwapFailed));
+
+            assert_eq!(pol_cost(30, 100, 1000, 3999), (300, 1199));
+            assert_eq!(pol_cost(100, 100, 1000, 3999), (1000, 3999));
+        }
+
+        #[ink::test]
+        fn check_price_works() {
+            assert_eq!(check_price(0), Err(Error::PriceUnavailable));
+            assert_eq!(check_price(100), Ok(100));
         }
     }
 }
@@ -0,0 +1,136 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Swap adapter between `Stable` and the exchange it trades ELC and ELP on.
+//!
+//! `Stable` only talks to an exchange through `SwapAdapter`. It keeps the address of an
+//! adapter contract and calls it by the selectors below, so any exchange can be plugged in
+//! by deploying an adapter with these messages. The in-repo `exchange` contract is a
+//! constant-product pool exposing them directly, priced by `input_price`.
+
+/// ELP is the native token, ELC a PSP22 token.
+pub type Balance = u128;
+
+/// Fee of the constant-product pool, base 1000.
+pub const FEE: u128 = 3;
+
+/// Selectors of the adapter messages, derived from the message names like ink! does.
+/// `quote_elc_to_elp(elc_in: Balance) -> Balance`
+pub const QUOTE_ELC_TO_ELP: [u8; 4] = [0x50, 0x69, 0xe7, 0xba];
+/// `quote_elp_to_elc(elp_in: Balance) -> Balance`
+pub const QUOTE_ELP_TO_ELC: [u8; 4] = [0xf2, 0x62, 0xa0, 0xff];
+/// `swap_elc_to_elp(elc_in: Balance, min_out: Balance) -> Result<Balance, Error>`
+pub const SWAP_ELC_TO_ELP: [u8; 4] = [0xb9, 0x0f, 0xc1, 0xe3];
+/// `swap_elp_to_elc(min_out: Balance) -> Result<Balance, Error>`, payable.
+pub const SWAP_ELP_TO_ELC: [u8; 4] = [0x83, 0xc7, 0x44, 0x08];
+/// `reserves() -> (Balance, Balance)`
+pub const RESERVES: [u8; 4] = [0xf3, 0x53, 0x5d, 0xf9];
+/// `add_liquidity(elc_amount: Balance) -> Result<Balance, Error>`, payable.
+pub const ADD_LIQUIDITY: [u8; 4] = [0x26, 0x4c, 0xd0, 0x4b];
+/// `remove_liquidity(shares: Balance) -> Result<(Balance, Balance), Error>`
+pub const REMOVE_LIQUIDITY: [u8; 4] = [0xbd, 0xd1, 0x6b, 0xfa];
+/// `total_shares() -> Balance`
+pub const TOTAL_SHARES: [u8; 4] = [0xac, 0xa1, 0x9d, 0x26];
+
+/// Trade access to an ELP/ELC pool.
+pub trait SwapAdapter {
+    /// Error of a failed trade or liquidity change.
+    type Error;
+
+    /// ELP got for selling `elc_in` ELC.
+    fn quote_elc_to_elp(&self, elc_in: Balance) -> Balance;
+
+    /// ELC got for selling `elp_in` ELP.
+    fn quote_elp_to_elc(&self, elp_in: Balance) -> Balance;
+
+    /// Sell `elc_in` ELC approved to the exchange for at least `min_out` ELP,
+    /// returns the ELP received.
+    fn swap_elc_to_elp(&mut self, elc_in: Balance, min_out: Balance) -> Result<Balance, Self::Error>;
+
+    /// Sell `elp_in` ELP sent with the call for at least `min_out` ELC,
+    /// returns the ELC received.
+    fn swap_elp_to_elc(&mut self, elp_in: Balance, min_out: Balance) -> Result<Balance, Self::Error>;
+
+    /// Returns `(elp_reserve, elc_reserve)` of the pool.
+    fn reserves(&self) -> (Balance, Balance);
+
+    /// Add `elp_amount` ELP sent with the call and `elc_amount` ELC approved to the exchange,
+    /// returns the pool shares minted.
+    fn add_liquidity(&mut self, elp_amount: Balance, elc_amount: Balance) -> Result<Balance, Self::Error>;
+
+    /// Burn `shares` of the caller, returns the `(elp, elc)` received.
+    fn remove_liquidity(&mut self, shares: Balance) -> Result<(Balance, Balance), Self::Error>;
+
+    /// Pool shares issued to all providers.
+    fn total_shares(&self) -> Balance;
+}
+
+/// Least output accepted for a trade quoted at `quote`, `tolerance` base 10000.
+pub fn min_out(quote: Balance, tolerance: u128) -> Balance {
+    quote - quote * tolerance / 10000
+}
+
+/// Output of selling `input` into a constant-product pool, the fee stays in the pool.
+pub fn input_price(input: Balance, input_reserve: Balance, output_reserve: Balance) -> Balance {
+    if input_reserve == 0 || output_reserve == 0 {
+        return 0
+    }
+    let input_with_fee = input * (1000 - FEE);
+    input_with_fee * output_reserve / (input_reserve * 1000 + input_with_fee)
+}
+
+/// Shares minted for adding `elp_in` and `elc_in`, the first deposit gets `elp_in` shares.
+/// Later deposits get the smaller side of their pool share, the excess stays in the pool.
+pub fn shares_for(
+    elp_in: Balance,
+    elc_in: Balance,
+    elp_reserve: Balance,
+    elc_reserve: Balance,
+    total_shares: Balance,
+) -> Balance {
+    if total_shares == 0 || elp_reserve == 0 || elc_reserve == 0 {
+        return elp_in
+    }
+    let by_elp = elp_in * total_shares / elp_reserve;
+    let by_elc = elc_in * total_shares / elc_reserve;
+    by_elp.min(by_elc)
+}
+
+/// Part of `reserve` owned by `shares` of `total_shares`.
+pub fn share_of(shares: Balance, total_shares: Balance, reserve: Balance) -> Balance {
+    if total_shares == 0 {
+        return 0
+    }
+    shares * reserve / total_shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_price_works() {
+        assert_eq!(input_price(100, 0, 1000), 0);
+        // 100 * 997 * 1000 / (1000 * 1000 + 100 * 997)
+        assert_eq!(input_price(100, 1000, 1000), 90);
+        // the output never drains the pool.
+        assert!(input_price(u64::MAX as u128, 1000, 1000) < 1000);
+    }
+
+    #[test]
+    fn min_out_works() {
+        assert_eq!(min_out(1000, 100), 990);
+        assert_eq!(min_out(1000, 0), 1000);
+        assert_eq!(min_out(0, 100), 0);
+    }
+
+    #[test]
+    fn shares_work() {
+        assert_eq!(shares_for(100, 400, 0, 0, 0), 100);
+        assert_eq!(shares_for(100, 400, 1000, 4000, 1000), 100);
+        // excess ELC earns nothing.
+        assert_eq!(shares_for(100, 800, 1000, 4000, 1000), 100);
+        assert_eq!(shares_for(50, 400, 1000, 4000, 1000), 50);
+        assert_eq!(share_of(100, 1000, 4000), 400);
+        assert_eq!(share_of(100, 0, 4000), 0);
+    }
+}
@@ -0,0 +1,433 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use self::vault::{Vault, VaultRef};
+use ink_lang as ink;
+
+/// Auto-compounding vault of RELP.
+///
+/// The vault holds RELP for its depositors and harvests the coinday rewards of it:
+/// ELP rewards are added back to `Stable` as liquidity for more RELP, ELC rewards and the
+/// ELC minted by `add_liquidity` are accounted per share when harvested, and paid out
+/// to the holders of the shares at that time on deposit and withdraw.
+#[ink::contract]
+mod vault {
+    use elc::ELCRef;
+    use relp::RELPRef;
+    use reward::RewardRef;
+    use additional::AdditionalRef;
+    use stable::StableRef;
+    use ink_prelude::vec;
+    use ink_storage::{
+        collections::HashMap as StorageHashMap,
+        lazy::Lazy,
+    };
+    use ink_env::call::FromAccountId;
+    use ink_lang::codegen::TraitCallBuilder;
+
+    /// The Vault error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        InvalidAmount,
+        InsufficientShares,
+        TransferFailed,
+        AddLiquidityFailed,
+    }
+
+    /// The Vault result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Precision of `elc_per_share`.
+    const ACC_PRECISION: u128 = 1_000_000_000_000;
+    /// Virtual shares and RELP added to both sides of the share price, so that RELP donated
+    /// to an almost empty vault cannot round the shares of the next depositor down to zero.
+    const VIRTUAL_SHARES: u128 = 1000;
+
+    #[ink(storage)]
+    pub struct Vault {
+        /// Mapping from depositor to its vault shares.
+        shares: StorageHashMap<AccountId, Balance>,
+        /// Total vault shares.
+        total_shares: Balance,
+        /// Harvested ELC per share, accumulated and scaled by `ACC_PRECISION`.
+        elc_per_share: u128,
+        /// Mapping from depositor to the ELC of its shares already paid out or earned
+        /// before it held them, scaled by `ACC_PRECISION`.
+        elc_debts: StorageHashMap<AccountId, u128>,
+        elc_contract: Lazy<ELCRef>,
+        relp_contract: Lazy<RELPRef>,
+        reward_contract: Lazy<RewardRef>,
+        add_contract: Lazy<AdditionalRef>,
+        stable_contract: Lazy<StableRef>,
+    }
+
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        user: AccountId,
+        relp_amount: Balance,
+        shares: Balance,
+        elc_amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        user: AccountId,
+        shares: Balance,
+        relp_amount: Balance,
+        elc_amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Harvested {
+        #[ink(topic)]
+        caller: AccountId,
+        elc_amount: Balance,
+        elp_amount: Balance,
+        relp_minted: Balance,
+    }
+
+    impl Vault {
+        #[ink(constructor)]
+        pub fn new(
+            elc_token: AccountId,
+            relp_token: AccountId,
+            reward_addr: AccountId,
+            additional_addr: AccountId,
+            stable_addr: AccountId,
+        ) -> Self {
+            let elc_contract: ELCRef = FromAccountId::from_account_id(elc_token);
+            let relp_contract: RELPRef = FromAccountId::from_account_id(relp_token);
+            let reward_contract: RewardRef = FromAccountId::from_account_id(reward_addr);
+            let add_contract: AdditionalRef = FromAccountId::from_account_id(additional_addr);
+            let stable_contract: StableRef = FromAccountId::from_account_id(stable_addr);
+            Self {
+                shares: StorageHashMap::new(),
+                total_shares: 0,
+                elc_per_share: 0,
+                elc_debts: StorageHashMap::new(),
+                elc_contract: Lazy::new(elc_contract),
+                relp_contract: Lazy::new(relp_contract),
+                reward_contract: Lazy::new(reward_contract),
+                add_contract: Lazy::new(add_contract),
+                stable_contract: Lazy::new(stable_contract),
+            }
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, user: AccountId) -> Balance {
+            self.shares.get(&user).copied().unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// RELP held by the vault.
+        #[ink(message)]
+        pub fn total_relp(&self) -> Balance {
+            self.relp_contract.balance_of(self.env().account_id())
+        }
+
+        /// ELC held by the vault, harvested but not paid out yet.
+        #[ink(message)]
+        pub fn total_elc(&self) -> Balance {
+            self.elc_contract.balance_of(self.env().account_id())
+        }
+
+        /// Harvested ELC of `user` not paid out yet.
+        #[ink(message)]
+        pub fn pending_elc_of(&self, user: AccountId) -> Balance {
+            let debt = self.elc_debts.get(&user).copied().unwrap_or(0);
+            pending_elc(self.shares_of(user), self.elc_per_share, debt)
+        }
+
+        /// Deposit `amount` RELP of the caller, which must be approved to the vault first.
+        /// Rewards are harvested before pricing the shares, with at least `min_relp_out`
+        /// RELP from the harvested ELP, and the pending ELC of the caller is paid out.
+        /// Shares are issued for the RELP received, after any transfer fee.
+        /// Returns the issued shares.
+        #[ink(message)]
+        pub fn deposit(&mut self, amount: Balance, min_relp_out: Balance) -> Result<Balance> {
+            let caller = self.env().caller();
+            self.harvest(min_relp_out)?;
+            let relp_before = self.total_relp();
+            if shares_for(amount, self.total_shares, relp_before) == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            let vault = self.env().account_id();
+            if self.relp_contract.transfer_from(caller, vault, amount).is_err() {
+                return Err(Error::TransferFailed)
+            }
+            // 按实际收到的RELP发放份额，转账手续费由存入者承担
+            let received = self.total_relp() - relp_before;
+            let shares = shares_for(received, self.total_shares, relp_before);
+            assert!(shares > 0, "Received RELP too small for a share");
+            let elc_amount = self.mint_shares(caller, shares);
+            self.pay_elc(caller, elc_amount);
+            self.env().emit_event(Deposit { user: caller, relp_amount: received, shares, elc_amount });
+            Ok(shares)
+        }
+
+        /// Burn `shares` of the caller for its part of the RELP held by the vault, and pay out
+        /// the pending ELC of all its shares. Call `harvest` first to include the rewards not
+        /// harvested yet. Returns `(relp_amount, elc_amount)`.
+        #[ink(message)]
+        pub fn withdraw(&mut self, shares: Balance) -> Result<(Balance, Balance)> {
+            let caller = self.env().caller();
+            let user_shares = self.shares_of(caller);
+            if shares == 0 {
+                return Err(Error::InvalidAmount)
+            }
+            if user_shares < shares {
+                return Err(Error::InsufficientShares)
+            }
+            let relp_amount = amount_for(shares, self.total_shares, self.total_relp());
+            let elc_amount = self.burn_shares(caller, shares);
+
+            // 转账失败时回滚上面的份额变更
+            if relp_amount > 0 {
+                assert!(self.relp_contract.transfer(caller, relp_amount).is_ok(), "RELP transfer failed");
+            }
+            self.pay_elc(caller, elc_amount);
+            self.env().emit_event(Withdraw { user: caller, shares, relp_amount, elc_amount });
+            Ok((relp_amount, elc_amount))
+        }
+
+        /// Claim the ELC and ELP rewards of the vault and add the ELP to `Stable` as liquidity,
+        /// failing when less than `min_relp_out` RELP is minted. The harvested ELC is added to
+        /// the ELC per share. Anyone may call it. Returns the RELP minted to the vault.
+        #[ink(message)]
+        pub fn harvest(&mut self, min_relp_out: Balance) -> Result<Balance> {
+            let vault = self.env().account_id();
+            let elc_before = self.total_elc();
+
+            // 结算vault的币天奖励，没有待领取的奖励时直接跳过
+            let _ = self.relp_contract.liquidate_increase_rewards(vec![vault]);
+            let _ = self.relp_contract.liquidate_block_rewards(vec![vault]);
+            // 开启线性释放时领取已释放部分
+            let _ = self.add_contract.withdraw_vested();
+            let mut elp_amount = self.reward_contract.claim().unwrap_or(0);
+            elp_amount += self.reward_contract.withdraw_vested().unwrap_or(0);
+
+            // ELP再次加入流动性，获得更多relp
+            let mut relp_minted = 0;
+            if elp_amount > 0 {
+                let now: u128 = self.env().block_timestamp().into();
+                let (relp_tokens, _) = self.stable_contract
+                    .call_mut()
+                    .add_liquidity(min_relp_out, 0, now)
+                    .transferred_value(elp_amount)
+                    .fire()
+                    .map_err(|_| Error::AddLiquidityFailed)?
+                    .map_err(|_| Error::AddLiquidityFailed)?;
+                relp_minted = relp_tokens;
+            }
+
+            let elc_amount = self.total_elc() - elc_before;
+            self.distribute_elc(elc_amount);
+            self.env().emit_event(Harvested {
+                caller: self.env().caller(),
+                elc_amount,
+                elp_amount,
+                relp_minted,
+            });
+            Ok(relp_minted)
+        }
+
+        /// Add `shares` to `user`, returns the pending ELC of its old shares to pay out.
+        fn mint_shares(&mut self, user: AccountId, shares: Balance) -> Balance {
+            let elc_amount = self.pending_elc_of(user);
+            let user_shares = self.shares_of(user) + shares;
+            self.shares.insert(user, user_shares);
+            self.elc_debts.insert(user, user_shares * self.elc_per_share);
+            self.total_shares += shares;
+            elc_amount
+        }
+
+        /// Remove `shares` of `user`, returns the pending ELC of all its shares to pay out.
+        fn burn_shares(&mut self, user: AccountId, shares: Balance) -> Balance {
+            let elc_amount = self.pending_elc_of(user);
+            let user_shares = self.shares_of(user) - shares;
+            self.shares.insert(user, user_shares);
+            self.elc_debts.insert(user, user_shares * self.elc_per_share);
+            self.total_shares -= shares;
+            elc_amount
+        }
+
+        /// Spread `elc_amount` harvested ELC over the current shares.
+        fn distribute_elc(&mut self, elc_amount: Balance) {
+            // 没有份额时收获的ELC留给之后的存入者
+            if elc_amount > 0 && self.total_shares > 0 {
+                self.elc_per_share += elc_amount * ACC_PRECISION / self.total_shares;
+            }
+        }
+
+        /// Pay `amount` harvested ELC to `to`. Panics on failure, so the share writes made
+        /// before the payment are reverted.
+        fn pay_elc(&mut self, to: AccountId, amount: Balance) {
+            if amount > 0 && self.elc_contract.transfer(to, amount).is_err() {
+                panic!("ELC transfer failed")
+            }
+        }
+    }
+
+    /// Shares issued for `amount` RELP deposited into a vault holding `total_relp`,
+    /// priced with `VIRTUAL_SHARES` on both sides.
+    fn shares_for(amount: Balance, total_shares: Balance, total_relp: Balance) -> Balance {
+        amount * (total_shares + VIRTUAL_SHARES) / (total_relp + VIRTUAL_SHARES)
+    }
+
+    /// ELC earned by `shares` at `elc_per_share` and not covered by `debt`.
+    fn pending_elc(shares: Balance, elc_per_share: u128, debt: u128) -> Balance {
+        (shares * elc_per_share).saturating_sub(debt) / ACC_PRECISION
+    }
+
+    /// Part of `total` owned by `shares`, priced like `shares_for`. The last shares take
+    /// the whole rest.
+    fn amount_for(shares: Balance, total_shares: Balance, total: Balance) -> Balance {
+        if total_shares == 0 {
+            return 0
+        }
+        if shares == total_shares {
+            return total
+        }
+        (shares * (total + VIRTUAL_SHARES) / (total_shares + VIRTUAL_SHARES)).min(total)
+    }
+
+    /// Unit tests
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+        use ink_lang as ink;
+        use ink_env::{test, DefaultEnvironment};
+
+        type Accounts = test::DefaultAccounts<DefaultEnvironment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts().expect("Cannot get accounts.")
+        }
+
+        fn new_vault() -> Vault {
+            Vault::new(
+                AccountId::from([0x1; 32]),
+                AccountId::from([0x2; 32]),
+                AccountId::from([0x3; 32]),
+                AccountId::from([0x4; 32]),
+                AccountId::from([0x5; 32]))
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let vault = new_vault();
+            let accounts = default_accounts();
+            assert_eq!(vault.total_shares(), 0);
+            assert_eq!(vault.shares_of(accounts.alice), 0);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn shares_for_works() {
+            // first deposit gets shares 1:1.
+            assert_eq!(shares_for(100, 0, 0), 100);
+            // compounded rewards raise the value of a share.
+            assert_eq!(shares_for(1000, 1000, 3000), 500);
+            assert_eq!(shares_for(1, 1000, 3000), 0);
+        }
+
+        #[ink::test]
+        fn amount_for_works() {
+            assert_eq!(amount_for(500, 1000, 3000), 1000);
+            assert_eq!(amount_for(1000, 1000, 3000), 3000);
+            assert_eq!(amount_for(10, 0, 200), 0);
+        }
+
+        #[ink::test]
+        fn donation_does_not_inflate_shares() {
+            // attacker deposits 1 RELP and donates 10000 to the vault.
+            let attacker_shares = shares_for(1, 0, 0);
+            let total_relp = 1 + 10000;
+            // the next deposit still gets shares, the donation mostly goes to the virtual ones.
+            let shares = shares_for(5000, attacker_shares, total_relp);
+            assert_eq!(shares, 454);
+            let total_shares = attacker_shares + shares;
+            assert_eq!(amount_for(attacker_shares, total_shares, total_relp + 5000), 10);
+            assert_eq!(amount_for(shares, total_shares, total_relp + 5000), 4992);
+        }
+
+        #[ink::test]
+        fn deposit_accounting_works() {
+            let mut vault = new_vault();
+            let accounts = default_accounts();
+            assert_eq!(vault.mint_shares(accounts.alice, 100), 0);
+            vault.distribute_elc(50);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 50);
+
+            // bob's shares earn nothing of the ELC harvested before.
+            assert_eq!(vault.mint_shares(accounts.bob, 100), 0);
+            assert_eq!(vault.pending_elc_of(accounts.bob), 0);
+            vault.distribute_elc(100);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 100);
+            assert_eq!(vault.pending_elc_of(accounts.bob), 50);
+
+            // alice is paid out on her next deposit.
+            assert_eq!(vault.mint_shares(accounts.alice, 50), 100);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 0);
+            assert_eq!(vault.shares_of(accounts.alice), 150);
+            assert_eq!(vault.total_shares(), 250);
+        }
+
+        #[ink::test]
+        fn withdraw_accounting_works() {
+            let mut vault = new_vault();
+            let accounts = default_accounts();
+            vault.mint_shares(accounts.alice, 100);
+            vault.mint_shares(accounts.bob, 300);
+            vault.distribute_elc(400);
+
+            // the pending ELC of all alice's shares is paid out.
+            assert_eq!(vault.burn_shares(accounts.alice, 40), 100);
+            assert_eq!(vault.shares_of(accounts.alice), 60);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 0);
+            assert_eq!(vault.total_shares(), 360);
+
+            vault.distribute_elc(360);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 60);
+            assert_eq!(vault.pending_elc_of(accounts.bob), 600);
+            assert_eq!(vault.burn_shares(accounts.bob, 300), 600);
+            assert_eq!(vault.shares_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn distribute_elc_without_shares_is_kept() {
+            let mut vault = new_vault();
+            let accounts = default_accounts();
+            vault.distribute_elc(100);
+            vault.mint_shares(accounts.alice, 100);
+            assert_eq!(vault.pending_elc_of(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn withdraw_failed() {
+            let mut vault = new_vault();
+            assert_eq!(vault.withdraw(0), Err(Error::InvalidAmount));
+            assert_eq!(vault.withdraw(10), Err(Error::InsufficientShares));
+        }
+
+        #[ink::test]
+        fn pending_elc_works() {
+            // 100 ELC harvested over 50 shares.
+            let per_share = 100 * ACC_PRECISION / 50;
+            assert_eq!(pending_elc(50, per_share, 0), 100);
+            // shares bought after the harvest earn nothing of it.
+            assert_eq!(pending_elc(30, per_share, 30 * per_share), 0);
+            // 60 more ELC over 80 shares.
+            let per_share = per_share + 60 * ACC_PRECISION / 80;
+            assert_eq!(pending_elc(50, per_share, 0), 137);
+            assert_eq!(pending_elc(30, per_share, 30 * 2 * ACC_PRECISION), 22);
+        }
+    }
+}
@@ -46,7 +46,7 @@ describe('\nProposals and Votes', () => {
 
 		it('deploy additional contract', async () => {
 			const additionalFactory = await getContractFactory('additional', Alice);
-			additional = await additionalFactory.deploy('new');
+			additional = await additionalFactory.deploy('new', elc.address);
 			expect(additional.address).to.exist;
 		});
 
@@ -70,8 +70,8 @@ describe('\nProposals and Votes', () => {
 
     describe('\nGovernance func test', async function() {
         it("Test1: Authorize to govern contract address", async () => {
-            await relp.tx.transferOwnership(govern.address); 
-            expect((relp.query.owner()).output).to.equal(govern.address);
+            await relp.tx.grantLocker(govern.address);
+            expect((await relp.query.isLocker(govern.address)).output).to.equal(true);
         });
     });
 });
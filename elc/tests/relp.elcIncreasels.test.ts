@@ -65,7 +65,7 @@ describe('\nELC incremental awards to RELP holders', () => {
 
         it('deploy additional contract', async () => {
             const additionalFactory = await getContractFactory('additional', Alice);
-            additional = await additionalFactory.deploy('new');
+            additional = await additionalFactory.deploy('new', elc.address);
 
             expect(additional.address).to.exist;
         });